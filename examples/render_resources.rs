@@ -53,6 +53,7 @@ fn print_sizes(memory_usage: Res<MemoryUsage>) {
     let mesh_stats = memory_usage.get_stats::<Mesh>().unwrap();
     let gpu_mesh_stats = memory_usage.get_stats::<GpuMesh>().unwrap();
     let image_stats = memory_usage.get_stats::<Image>().unwrap();
+    let gpu_image_stats = memory_usage.get_stats::<GpuImage>().unwrap();
     let material_stats = memory_usage.get_stats::<StandardMaterial>().unwrap();
 
     println!();
@@ -60,5 +61,6 @@ fn print_sizes(memory_usage: Res<MemoryUsage>) {
     println!("Meshes: {mesh_stats}");
     println!("GPU Meshes: {gpu_mesh_stats}");
     println!("Images: {image_stats}");
+    println!("GPU Images: {gpu_image_stats}");
     println!("Materials: {material_stats}");
 }
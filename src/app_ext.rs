@@ -7,17 +7,20 @@ use bevy::{
     asset::Asset,
     ecs::{
         component::Component, schedule::IntoSystemDescriptor, schedule::StageLabel,
-        system::Resource,
+        system::Resource, world::Mut,
     },
     prelude::CoreStage,
 };
 
 #[cfg(feature = "bevy_render")]
-use bevy::render::{render_asset::RenderAsset, RenderApp, RenderStage};
+use bevy::render::{
+    render_asset::{ExtractedAssets, RenderAsset},
+    RenderApp, RenderStage,
+};
 
 use crate::{
     estimator::{ForwardingEstimator, FromConfig},
-    systems, DataSize, DataSizeEstimator, MemoryUsage,
+    systems, DataSize, DataSizeEstimator, HandleReferences, MemoryUsage, TypeKind,
 };
 
 /// [`App`] extension methods to register [`DataSize`] types for memory usage
@@ -70,8 +73,13 @@ pub trait RegisterSizedTypes: RegisterTypesWithEstimator {
     /// resource:
     ///
     /// * `T`
-    /// * `<T as RenderAsset>::ExtractedAsset`
+    /// * [`ExtractedAssets<T>`], the `<T as RenderAsset>::ExtractedAsset`s
+    ///   that are queued in the render world, which are not measured
+    ///   themselves: their heap size is estimated from the `T` they were
+    ///   extracted from, which assumes that they hold the same data
     /// * `<T as RenderAsset>::PreparedAsset`
+    ///
+    /// [`ExtractedAssets<T>`]: bevy::render::render_asset::ExtractedAssets
     #[cfg(feature = "bevy_render")]
     fn register_sized_render_asset<T>(&mut self) -> &mut Self
     where
@@ -89,25 +97,24 @@ impl RegisterSizedTypes for App {}
 pub trait RegisterTypesWithEstimator: RegisterTypes {
     /// Like [`RegisterSizedTypes::register_sized_component`], but uses the
     /// given [`DataSizeEstimator`] type.
+    ///
+    /// The estimator is also used by [`MemoryUsage::measure_entity`], so
+    /// implementations have to record it with
+    /// [`MemoryUsage::register_component_estimator`].
     fn register_component_with_estimator<T, E>(&mut self) -> &mut Self
     where
         T: Any + Component,
-        E: DataSizeEstimator<T> + FromConfig + 'static,
-    {
-        self.register_type::<T, _, _, _>(
-            systems::update_stats_for_component::<T, E>,
-            CoreStage::Update,
-        )
-    }
+        E: DataSizeEstimator<T> + FromConfig + Send + 'static;
 
     /// Like [`RegisterSizedTypes::register_sized_resource`], but uses the
     /// given [`DataSizeEstimator`] type.
     fn register_resource_with_estimator<T, E>(&mut self) -> &mut Self
     where
         T: Any + Resource,
-        E: DataSizeEstimator<T> + FromConfig + 'static,
+        E: DataSizeEstimator<T> + FromConfig + Send + 'static,
     {
-        self.register_type::<T, _, _, _>(
+        self.register_type_with_kind::<T, _, _, _>(
+            TypeKind::Resource,
             systems::update_stats_for_resource::<T, E>,
             CoreStage::Update,
         )
@@ -115,55 +122,170 @@ pub trait RegisterTypesWithEstimator: RegisterTypes {
 
     /// Like [`RegisterSizedTypes::register_sized_asset`], but uses the
     /// given [`DataSizeEstimator`] type.
+    ///
+    /// The estimator is also used by [`MemoryUsage::measure_subtree`] for
+    /// assets that are referenced by a [`Handle`], so implementations have to
+    /// record it with [`MemoryUsage::register_asset_estimator`].
+    ///
+    /// [`Handle`]: bevy::asset::Handle
     fn register_asset_with_estimator<T, E>(&mut self) -> &mut Self
     where
         T: Any + Asset,
-        E: DataSizeEstimator<T> + FromConfig + 'static,
-    {
-        self.register_type::<T, _, _, _>(systems::update_stats_for_asset::<T, E>, CoreStage::Update)
-    }
+        E: DataSizeEstimator<T> + FromConfig + Send + 'static;
 
-    /// Like [`RegisterSizedTypes::register_sized_asset`], but uses the given
-    /// [`DataSizeEstimator`] types to estimate the size of the [`RenderAsset`]
-    /// and its prepared format.
+    /// Like [`RegisterSizedTypes::register_sized_render_asset`], but uses the
+    /// given [`DataSizeEstimator`] types to estimate the size of the
+    /// [`RenderAsset`] (`E`) and its prepared format (`F`).
+    ///
+    /// The extracted format is assumed to hold the same data as the
+    /// [`RenderAsset`] it was extracted from, so its heap and device sizes are
+    /// estimated from that asset with `E`. This is the case for the render
+    /// assets built into Bevy, whose `ExtractedAsset` is `T` itself.
     #[cfg(feature = "bevy_render")]
     fn register_render_asset_with_estimator<T, E, F>(&mut self) -> &mut Self
     where
         T: Any + RenderAsset,
-        E: DataSizeEstimator<T> + FromConfig + 'static,
+        E: DataSizeEstimator<T> + FromConfig + Send + 'static,
         <T as RenderAsset>::PreparedAsset: Any,
-        F: DataSizeEstimator<<T as RenderAsset>::PreparedAsset> + FromConfig + 'static;
-}
+        F: DataSizeEstimator<<T as RenderAsset>::PreparedAsset> + FromConfig + Send + 'static,
+    {
+        self.register_render_asset_with_extracted_estimator::<T, E, F, E>()
+    }
 
-impl RegisterTypesWithEstimator for App {
+    /// Like [`register_render_asset_with_estimator`], but estimates the size
+    /// of the extracted format of each [`RenderAsset`] with `G`.
+    ///
+    /// `G` is an estimator for the source asset `T`, not for its
+    /// `ExtractedAsset`: it is given the asset in the main world that the
+    /// extracted format is copied from, so that the asset does not have to be
+    /// extracted again just to be measured. It should return the heap and
+    /// device sizes of the extracted format that would be copied from the
+    /// asset. The stack size is always that of the `ExtractedAsset` type.
+    ///
+    /// [`register_render_asset_with_estimator`]: Self::register_render_asset_with_estimator
     #[cfg(feature = "bevy_render")]
-    fn register_render_asset_with_estimator<T, E, F>(&mut self) -> &mut Self
+    fn register_render_asset_with_extracted_estimator<T, E, F, G>(&mut self) -> &mut Self
     where
         T: Any + RenderAsset,
-        E: DataSizeEstimator<T> + FromConfig + 'static,
+        E: DataSizeEstimator<T> + FromConfig + Send + 'static,
         <T as RenderAsset>::PreparedAsset: Any,
-        F: DataSizeEstimator<<T as RenderAsset>::PreparedAsset> + FromConfig + 'static,
+        F: DataSizeEstimator<<T as RenderAsset>::PreparedAsset> + FromConfig + Send + 'static,
+        G: DataSizeEstimator<T> + FromConfig + Send + 'static;
+
+    /// Uses the given [`HandleReferences`] type to find the asset handles held
+    /// by the already registered [`Component`] type `T`.
+    ///
+    /// The handles are followed by [`MemoryUsage::measure_entity_deep`].
+    fn register_component_handle_references<T, R>(&mut self) -> &mut Self
+    where
+        T: Any + Component,
+        R: HandleReferences<T> + FromConfig + Send + 'static;
+
+    /// Uses the given [`HandleReferences`] type to find the asset handles held
+    /// by the already registered [`Asset`] type `T`.
+    ///
+    /// The handles are followed by [`MemoryUsage::measure_entity_deep`] and
+    /// [`MemoryUsage::measure_asset_deep`].
+    fn register_asset_handle_references<T, R>(&mut self) -> &mut Self
+    where
+        T: Any + Asset,
+        R: HandleReferences<T> + FromConfig + Send + 'static;
+}
+
+impl RegisterTypesWithEstimator for App {
+    fn register_component_with_estimator<T, E>(&mut self) -> &mut Self
+    where
+        T: Any + Component,
+        E: DataSizeEstimator<T> + FromConfig + Send + 'static,
     {
-        RegisterTypes::register_type::<T, _, _, _>(
+        RegisterTypes::register_type_with_kind::<T, _, _, _>(
             self,
-            systems::update_stats_for_asset::<T, E>,
+            TypeKind::Component,
+            systems::update_stats_for_component::<T, E>,
             CoreStage::Update,
         );
 
+        // Runs in the last stage so that components removed by commands
+        // earlier in the frame are seen by the incremental tracking.
+        RegisterTypes::register_type_with_kind::<T, _, _, _>(
+            self,
+            TypeKind::Component,
+            systems::update_stats_for_changed_components::<T, E>,
+            CoreStage::Last,
+        );
+
+        get_memory_usage(self).register_component_estimator::<T, E>();
+
+        self
+    }
+
+    fn register_asset_with_estimator<T, E>(&mut self) -> &mut Self
+    where
+        T: Any + Asset,
+        E: DataSizeEstimator<T> + FromConfig + Send + 'static,
+    {
+        // Runs in the last stage so that it sees the asset events that are
+        // sent at the end of the frame.
+        RegisterTypes::register_type_with_kind::<T, _, _, _>(
+            self,
+            TypeKind::Asset,
+            systems::update_stats_for_asset::<T, E>,
+            CoreStage::Last,
+        );
+
+        get_memory_usage(self).register_asset_estimator::<T, E>();
+
+        self
+    }
+
+    #[cfg(feature = "bevy_render")]
+    fn register_render_asset_with_extracted_estimator<T, E, F, G>(&mut self) -> &mut Self
+    where
+        T: Any + RenderAsset,
+        E: DataSizeEstimator<T> + FromConfig + Send + 'static,
+        <T as RenderAsset>::PreparedAsset: Any,
+        F: DataSizeEstimator<<T as RenderAsset>::PreparedAsset> + FromConfig + Send + 'static,
+        G: DataSizeEstimator<T> + FromConfig + Send + 'static,
+    {
+        self.register_asset_with_estimator::<T, E>();
+
+        register_extracted_render_asset::<T, G>(self);
+
         if let Ok(render_app) = self.get_sub_app_mut(RenderApp) {
-            RegisterTypes::register_type::<<T as RenderAsset>::PreparedAsset, _, _, _>(
+            RegisterTypes::register_type_with_kind::<<T as RenderAsset>::PreparedAsset, _, _, _>(
                 render_app,
+                TypeKind::RenderAsset,
                 systems::update_stats_for_render_asset::<T, F>,
                 RenderStage::Queue,
             );
 
             // Also register the type on the main app so there are entries for
             // it in the hashmap.
-            register_type_on_app::<T>(self);
+            register_type_on_app::<T>(self, TypeKind::Asset);
         }
 
         self
     }
+
+    fn register_component_handle_references<T, R>(&mut self) -> &mut Self
+    where
+        T: Any + Component,
+        R: HandleReferences<T> + FromConfig + Send + 'static,
+    {
+        get_memory_usage(self).register_component_references::<T, R>();
+
+        self
+    }
+
+    fn register_asset_handle_references<T, R>(&mut self) -> &mut Self
+    where
+        T: Any + Asset,
+        R: HandleReferences<T> + FromConfig + Send + 'static,
+    {
+        get_memory_usage(self).register_asset_references::<T, R>();
+
+        self
+    }
 }
 
 /// The lowest-level interface for registering types for memory usage tracking.
@@ -172,7 +294,7 @@ pub trait RegisterTypes {
     /// `system`, which will run in the given `stage`.
     ///
     /// The given type `T` will be available to query on the [`MemoryUsage`]
-    /// resource.
+    /// resource, and will be reported as a [`TypeKind::Custom`] type.
     ///
     /// [`MemoryStats`]: crate::MemoryStats
     fn register_type<T, S, Params, L>(&mut self, system: S, stage: L) -> &mut Self
@@ -180,6 +302,27 @@ pub trait RegisterTypes {
         T: Any,
         S: IntoSystemDescriptor<Params>,
         L: StageLabel;
+
+    /// Like [`register_type`][Self::register_type], but the type will be
+    /// reported as the given [`TypeKind`].
+    ///
+    /// By default, this ignores the kind and calls
+    /// [`register_type`][Self::register_type].
+    fn register_type_with_kind<T, S, Params, L>(
+        &mut self,
+        kind: TypeKind,
+        system: S,
+        stage: L,
+    ) -> &mut Self
+    where
+        T: Any,
+        S: IntoSystemDescriptor<Params>,
+        L: StageLabel,
+    {
+        let _ = kind;
+
+        self.register_type::<T, S, Params, L>(system, stage)
+    }
 }
 
 impl RegisterTypes for App {
@@ -189,7 +332,21 @@ impl RegisterTypes for App {
         S: IntoSystemDescriptor<Params>,
         L: StageLabel,
     {
-        register_type_on_app::<T>(self);
+        self.register_type_with_kind::<T, S, Params, L>(TypeKind::Custom, system, stage)
+    }
+
+    fn register_type_with_kind<T, S, Params, L>(
+        &mut self,
+        kind: TypeKind,
+        system: S,
+        stage: L,
+    ) -> &mut Self
+    where
+        T: Any,
+        S: IntoSystemDescriptor<Params>,
+        L: StageLabel,
+    {
+        register_type_on_app::<T>(self, kind);
 
         self.add_system_to_stage(stage, system);
 
@@ -197,13 +354,36 @@ impl RegisterTypes for App {
     }
 }
 
-fn register_type_on_app<T>(app: &mut App)
+/// Registers the [`ExtractedAssets`] of the given render asset type `T`,
+/// whose size is estimated from the source assets with the given
+/// [`DataSizeEstimator`].
+///
+/// [`ExtractedAssets`]: bevy::render::render_asset::ExtractedAssets
+#[cfg(feature = "bevy_render")]
+pub(crate) fn register_extracted_render_asset<T, E>(app: &mut App)
+where
+    T: Any + RenderAsset,
+    E: DataSizeEstimator<T> + FromConfig + Send + 'static,
+{
+    if let Ok(render_app) = app.get_sub_app_mut(RenderApp) {
+        RegisterTypes::register_type_with_kind::<ExtractedAssets<T>, _, _, _>(
+            render_app,
+            TypeKind::RenderAsset,
+            systems::update_stats_for_extracted_render_asset::<T, E>,
+            RenderStage::Extract,
+        );
+    }
+}
+
+fn register_type_on_app<T>(app: &mut App, kind: TypeKind)
 where
     T: Any,
 {
-    let mut memory_usage = app.world.get_resource_mut::<MemoryUsage>().expect(
-        "Cannot find resource `MemoryUsage`. Did you forget to add the `MemoryUsagePlugin`?",
-    );
+    get_memory_usage(app).register_type_with_kind::<T>(kind);
+}
 
-    memory_usage.register_type::<T>();
+fn get_memory_usage(app: &mut App) -> Mut<MemoryUsage> {
+    app.world.get_resource_mut::<MemoryUsage>().expect(
+        "Cannot find resource `MemoryUsage`. Did you forget to add the `MemoryUsagePlugin`?",
+    )
 }
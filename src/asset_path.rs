@@ -0,0 +1,231 @@
+use std::{
+    fmt,
+    path::{Path, PathBuf},
+};
+
+use bevy::utils::HashMap;
+
+use crate::MemoryStats;
+
+/// The estimated memory usage of the tracked assets, grouped by the file they
+/// were loaded from and by the folders containing those files.
+///
+/// Assets that are loaded from the same file (such as the meshes and
+/// materials of a glTF scene) are attributed to that file together.
+///
+/// Use [`MemoryUsage::asset_path_report`] to create one.
+///
+/// [`MemoryUsage::asset_path_report`]: crate::MemoryUsage::asset_path_report
+#[derive(Debug, Clone, Default)]
+pub struct AssetPathReport {
+    files: Vec<(PathBuf, MemoryStats)>,
+    folders: Vec<(PathBuf, MemoryStats)>,
+    unattributed: MemoryStats,
+}
+
+impl AssetPathReport {
+    pub(crate) fn new<I>(assets: I) -> Self
+    where
+        I: IntoIterator<Item = (Option<PathBuf>, MemoryStats)>,
+    {
+        let mut files: HashMap<PathBuf, MemoryStats> = HashMap::default();
+        let mut unattributed = MemoryStats::default();
+
+        for (path, stats) in assets {
+            let total = match path {
+                Some(path) => files.entry(path).or_default(),
+                None => &mut unattributed,
+            };
+
+            *total = *total + stats;
+        }
+
+        let mut folders: HashMap<PathBuf, MemoryStats> = HashMap::default();
+
+        for (path, stats) in files.iter() {
+            for folder in path.ancestors().skip(1) {
+                if folder.as_os_str().is_empty() {
+                    continue;
+                }
+
+                let total = folders.entry(folder.to_path_buf()).or_default();
+                *total = *total + *stats;
+            }
+        }
+
+        Self {
+            files: sorted_by_size(files),
+            folders: sorted_by_size(folders),
+            unattributed,
+        }
+    }
+
+    /// Returns the summed [`MemoryStats`] of the assets loaded from each file,
+    /// largest first.
+    #[inline]
+    pub fn files(&self) -> &[(PathBuf, MemoryStats)] {
+        &self.files
+    }
+
+    /// Returns the summed [`MemoryStats`] of the assets under each folder,
+    /// including those in nested folders, largest first.
+    #[inline]
+    pub fn folders(&self) -> &[(PathBuf, MemoryStats)] {
+        &self.folders
+    }
+
+    /// Returns the summed [`MemoryStats`] of the assets loaded from the given
+    /// file.
+    pub fn get_file(&self, path: impl AsRef<Path>) -> Option<MemoryStats> {
+        let path = path.as_ref();
+
+        self.files
+            .iter()
+            .find(|(file, _)| file == path)
+            .map(|(_, stats)| *stats)
+    }
+
+    /// Returns the summed [`MemoryStats`] of all assets whose path starts with
+    /// the given prefix.
+    ///
+    /// The prefix is matched by whole path components, so `textures/terrain`
+    /// matches `textures/terrain/grass.png` but not
+    /// `textures/terrain_old/grass.png`.
+    pub fn get_folder(&self, prefix: impl AsRef<Path>) -> MemoryStats {
+        let prefix = prefix.as_ref();
+
+        self.files
+            .iter()
+            .filter(|(file, _)| file.starts_with(prefix))
+            .map(|(_, stats)| *stats)
+            .sum()
+    }
+
+    /// Returns the summed [`MemoryStats`] of the assets that were not loaded
+    /// through the [`AssetServer`], such as procedurally generated meshes.
+    ///
+    /// [`AssetServer`]: bevy::asset::AssetServer
+    #[inline]
+    pub fn unattributed(&self) -> MemoryStats {
+        self.unattributed
+    }
+
+    /// Returns the sum of all of the [`MemoryStats`] in the report, including
+    /// the unattributed assets.
+    pub fn total(&self) -> MemoryStats {
+        self.files
+            .iter()
+            .map(|(_, stats)| *stats)
+            .sum::<MemoryStats>()
+            + self.unattributed
+    }
+}
+
+fn sorted_by_size(paths: HashMap<PathBuf, MemoryStats>) -> Vec<(PathBuf, MemoryStats)> {
+    let mut paths: Vec<_> = paths.into_iter().collect();
+
+    paths.sort_by(|(a_path, a), (b_path, b)| {
+        b.total_including_device()
+            .cmp(&a.total_including_device())
+            .then_with(|| a_path.cmp(b_path))
+    });
+
+    paths
+}
+
+impl fmt::Display for AssetPathReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Asset memory usage by folder:")?;
+
+        for (folder, stats) in self.folders.iter() {
+            writeln!(f, "{}/**: {stats}", folder.display())?;
+        }
+
+        writeln!(f, "Asset memory usage by file:")?;
+
+        for (file, stats) in self.files.iter() {
+            writeln!(f, "{}: {stats}", file.display())?;
+        }
+
+        writeln!(f, "Unattributed: {}", self.unattributed)?;
+
+        write!(f, "Total: {}", self.total())
+    }
+}
+
+/***************************************************************************************************
+
+                             dMMMMMMP dMMMMMP .dMMMb dMMMMMMP .dMMMb
+                               dMP   dMP     dMP" VP   dMP   dMP" VP
+                              dMP   dMMMP    VMMMb    dMP    VMMMb
+                             dMP   dMP     dP .dMP   dMP   dP .dMP
+                            dMP   dMMMMMP  VMMMP"   dMP    VMMMP"
+
+***************************************************************************************************/
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stats(total_heap_bytes: usize) -> MemoryStats {
+        MemoryStats {
+            count: 1,
+            total_stack_bytes: 0,
+            total_heap_bytes,
+            total_device_bytes: 0,
+        }
+    }
+
+    #[test]
+    fn groups_assets_by_file_and_folder() {
+        let report = AssetPathReport::new(vec![
+            (Some("textures/terrain/grass.png".into()), stats(100)),
+            (Some("textures/terrain/rock.png".into()), stats(300)),
+            (Some("textures/ui/button.png".into()), stats(50)),
+            (Some("models/tree.gltf".into()), stats(20)),
+            (Some("models/tree.gltf".into()), stats(30)),
+            (None, stats(7)),
+        ]);
+
+        assert_eq!(
+            report.get_file("models/tree.gltf"),
+            Some(MemoryStats {
+                count: 2,
+                total_stack_bytes: 0,
+                total_heap_bytes: 50,
+                total_device_bytes: 0,
+            })
+        );
+        assert_eq!(report.get_file("models/bush.gltf"), None);
+
+        let folders: Vec<_> = report
+            .folders()
+            .iter()
+            .map(|(folder, stats)| (folder.to_str().unwrap(), stats.total_bytes()))
+            .collect();
+        assert_eq!(
+            folders,
+            vec![
+                ("textures", 450),
+                ("textures/terrain", 400),
+                ("models", 50),
+                ("textures/ui", 50)
+            ]
+        );
+
+        assert_eq!(report.unattributed().total_bytes(), 7);
+        assert_eq!(report.total().total_bytes(), 507);
+    }
+
+    #[test]
+    fn matches_folder_prefixes_by_component() {
+        let report = AssetPathReport::new(vec![
+            (Some("textures/terrain/grass.png".into()), stats(100)),
+            (Some("textures/terrain_old/grass.png".into()), stats(200)),
+        ]);
+
+        assert_eq!(report.get_folder("textures/terrain").total_bytes(), 100);
+        assert_eq!(report.get_folder("textures").total_bytes(), 300);
+        assert_eq!(report.get_folder("sounds").total_bytes(), 0);
+    }
+}
@@ -0,0 +1,223 @@
+//! Tracking for prepared render assets whose device memory has to be computed
+//! from their source asset in the main world.
+
+use std::{any::Any, marker::PhantomData};
+
+use bevy::{
+    app::App,
+    asset::{AssetEvent, Assets, HandleId},
+    ecs::{
+        event::EventReader,
+        schedule::IntoSystemDescriptor,
+        system::{Commands, Local, Res, ResMut},
+    },
+    render::{render_asset::RenderAsset, RenderApp, RenderStage},
+    utils::HashSet,
+};
+
+use crate::{
+    app_ext::RegisterTypes,
+    systems::{is_tracking_enabled, update_stats_from_cache, InstanceCache},
+    MemoryConfig, MemoryStats, MemoryUsage, TypeKind,
+};
+
+/// The device memory sizes of the assets of type `T` that changed since the
+/// last extraction, keyed by handle.
+///
+/// This is computed in the main world by an extract system and inserted into
+/// the render world, where it is applied to the cached sizes and emptied.
+#[derive(Debug)]
+pub(crate) struct ExtractedDeviceSizes<T> {
+    /// Whether `changed` holds the sizes of all assets and replaces the ones
+    /// that were sent before.
+    reset: bool,
+    changed: Vec<(HandleId, usize)>,
+    removed: Vec<HandleId>,
+    marker: PhantomData<fn() -> T>,
+}
+
+impl<T> Default for ExtractedDeviceSizes<T> {
+    fn default() -> Self {
+        Self {
+            reset: false,
+            changed: Vec::new(),
+            removed: Vec::new(),
+            marker: PhantomData,
+        }
+    }
+}
+
+/// Tracks the assets whose device memory sizes have to be sent to the render
+/// world on the next sampled frame.
+#[derive(Debug, Default)]
+pub(crate) struct DeviceSizeChanges {
+    valid: bool,
+    changed: HashSet<HandleId>,
+    removed: HashSet<HandleId>,
+}
+
+/// Computes the device memory size of each asset of type `T` with the given
+/// function, and sends the sizes to the render world.
+///
+/// This is meant to be called from the extract system that is passed to
+/// [`register_prepared_asset_with_device_sizes`]. After the sizes of all
+/// assets have been sent once, only the assets for which an [`AssetEvent`]
+/// was received are measured and sent again.
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn extract_device_sizes<T, F>(
+    assets: &Assets<T>,
+    asset_events: &mut EventReader<AssetEvent<T>>,
+    changes: &mut DeviceSizeChanges,
+    memory_config: &MemoryConfig,
+    memory_usage: &MemoryUsage,
+    render_commands: &mut Commands,
+    device_size: F,
+) where
+    T: RenderAsset,
+    <T as RenderAsset>::PreparedAsset: Any,
+    F: Fn(&T) -> usize,
+{
+    if !is_tracking_enabled::<<T as RenderAsset>::PreparedAsset>(memory_config) {
+        // The changes will go stale while they are not being tracked.
+        *changes = DeviceSizeChanges::default();
+        return;
+    }
+
+    let device_sizes = if changes.valid {
+        for event in asset_events.iter() {
+            match event {
+                AssetEvent::Created { handle } | AssetEvent::Modified { handle } => {
+                    changes.removed.remove(&handle.id);
+                    changes.changed.insert(handle.id);
+                }
+                AssetEvent::Removed { handle } => {
+                    changes.changed.remove(&handle.id);
+                    changes.removed.insert(handle.id);
+                }
+            }
+        }
+
+        if !memory_usage.is_sampling() || (changes.changed.is_empty() && changes.removed.is_empty())
+        {
+            return;
+        }
+
+        ExtractedDeviceSizes {
+            reset: false,
+            changed: changes
+                .changed
+                .drain()
+                .filter_map(|handle_id| {
+                    let asset = assets.get(handle_id)?;
+                    Some((handle_id, device_size(asset)))
+                })
+                .collect(),
+            removed: changes.removed.drain().collect(),
+            marker: PhantomData,
+        }
+    } else {
+        if !memory_usage.is_sampling() {
+            return;
+        }
+
+        for _event in asset_events.iter() {}
+
+        changes.valid = true;
+
+        ExtractedDeviceSizes {
+            reset: true,
+            changed: assets
+                .iter()
+                .map(|(handle_id, asset)| (handle_id, device_size(asset)))
+                .collect(),
+            removed: Vec::new(),
+            marker: PhantomData,
+        }
+    };
+
+    render_commands.insert_resource(device_sizes);
+}
+
+/// Registers the prepared asset type of `T` on the render sub-app.
+///
+/// Its [`MemoryStats`] are updated from the [`ExtractedDeviceSizes`] of `T`,
+/// which must be provided by the given extract system.
+pub(crate) fn register_prepared_asset_with_device_sizes<T, S, Params>(
+    app: &mut App,
+    extract_system: S,
+) where
+    T: RenderAsset,
+    S: IntoSystemDescriptor<Params>,
+    <T as RenderAsset>::PreparedAsset: Any,
+{
+    if let Ok(render_app) = app.get_sub_app_mut(RenderApp) {
+        render_app.add_system_to_stage(RenderStage::Extract, extract_system);
+
+        RegisterTypes::register_type_with_kind::<<T as RenderAsset>::PreparedAsset, _, _, _>(
+            render_app,
+            TypeKind::RenderAsset,
+            update_stats_for_prepared_asset::<T>,
+            RenderStage::Queue,
+        );
+    }
+}
+
+/// Updates the [`MemoryStats`] of the prepared asset type of `T`, using the
+/// [`ExtractedDeviceSizes`] of `T` as the device memory of each asset.
+///
+/// The extracted sizes are applied to the cached stats on every frame, so
+/// that no changes are missed. Assets that have been extracted but are still
+/// waiting to be prepared are counted as well.
+fn update_stats_for_prepared_asset<T>(
+    device_sizes: Option<ResMut<ExtractedDeviceSizes<T>>>,
+    mut cache: Local<InstanceCache<HandleId>>,
+    memory_config: Res<MemoryConfig>,
+    memory_usage: Res<MemoryUsage>,
+) where
+    T: RenderAsset,
+    <T as RenderAsset>::PreparedAsset: Any,
+{
+    if let Some(mut device_sizes) = device_sizes {
+        let ExtractedDeviceSizes {
+            reset,
+            changed,
+            removed,
+            ..
+        } = std::mem::take(&mut *device_sizes);
+
+        let stats_of = |device_size| MemoryStats {
+            count: 1,
+            total_stack_bytes: std::mem::size_of::<<T as RenderAsset>::PreparedAsset>(),
+            total_heap_bytes: 0,
+            total_device_bytes: device_size,
+        };
+
+        if reset {
+            cache.rebuild(
+                changed
+                    .into_iter()
+                    .map(|(handle_id, device_size)| (handle_id, stats_of(device_size))),
+            );
+        } else {
+            for (handle_id, device_size) in changed {
+                cache.insert(handle_id, stats_of(device_size));
+            }
+
+            for handle_id in removed.iter() {
+                cache.remove(handle_id);
+            }
+        }
+    }
+
+    if !is_tracking_enabled::<<T as RenderAsset>::PreparedAsset>(&*memory_config)
+        || !memory_usage.is_sampling()
+    {
+        return;
+    }
+
+    update_stats_from_cache::<<T as RenderAsset>::PreparedAsset, _>(
+        &*cache,
+        &*memory_config,
+        &*memory_usage,
+    );
+}
@@ -1,15 +1,42 @@
 //! Memory usage tracking for Bevy's [`Image`] type.
-use bevy::{app::Plugin, render::texture::Image};
+use bevy::{
+    app::Plugin,
+    asset::{AssetEvent, Assets, HandleId},
+    ecs::{
+        event::EventReader,
+        system::{Commands, Local, Res},
+    },
+    render::{
+        render_resource::{TextureDescriptor, TextureDimension},
+        texture::Image,
+    },
+};
 
-use crate::{app_ext::RegisterTypesWithEstimator, DataSize, DataSizeEstimator};
+use super::device::{
+    extract_device_sizes, register_prepared_asset_with_device_sizes, DeviceSizeChanges,
+};
+use crate::{
+    app_ext::{register_extracted_render_asset, RegisterTypesWithEstimator},
+    DataSize, DataSizeEstimator, MemoryConfig, MemoryUsage,
+};
 
-/// Adds memory tracking for [`Image`] assets.
+/// Adds memory tracking for [`Image`] and [`GpuImage`] assets, and for the
+/// copies of changed images that are extracted to the render world.
+///
+/// The device memory of each [`GpuImage`] is computed from the
+/// [`TextureDescriptor`] of its [`Image`], so it does not depend on the
+/// image's CPU-side data.
+///
+/// [`GpuImage`]: bevy::render::texture::GpuImage
 #[derive(Debug, Default)]
 pub struct ImageMemoryUsagePlugin;
 
 impl Plugin for ImageMemoryUsagePlugin {
     fn build(&self, app: &mut bevy::prelude::App) {
         app.register_asset_with_estimator::<Image, ImageSizeEstimator>();
+
+        register_extracted_render_asset::<Image, ImageSizeEstimator>(app);
+        register_prepared_asset_with_device_sizes::<Image, _, _>(app, extract_image_device_sizes);
     }
 }
 
@@ -25,3 +52,167 @@ impl DataSizeEstimator<Image> for ImageSizeEstimator {
         image.data.estimate_heap_size()
     }
 }
+
+/// Computes the device memory size of each [`Image`] from its
+/// [`TextureDescriptor`] and sends it to the render world.
+///
+/// The [`GpuImage`]s in the render world do not keep their descriptor around,
+/// so this is the only place where the sizes can be computed. They are only
+/// recomputed and sent again for images that changed.
+///
+/// [`GpuImage`]: bevy::render::texture::GpuImage
+fn extract_image_device_sizes(
+    images: Res<Assets<Image>>,
+    mut image_events: EventReader<AssetEvent<Image>>,
+    mut changes: Local<DeviceSizeChanges>,
+    memory_config: Res<MemoryConfig>,
+    memory_usage: Res<MemoryUsage>,
+    mut render_commands: Commands,
+) {
+    extract_device_sizes(
+        &*images,
+        &mut image_events,
+        &mut *changes,
+        &*memory_config,
+        &*memory_usage,
+        &mut render_commands,
+        |image: &Image| texture_device_size(&image.texture_descriptor),
+    );
+}
+
+/// Returns the number of bytes of device memory used by a texture with the
+/// given descriptor, including all of its mip levels, array layers, and
+/// samples.
+///
+/// Compressed formats are measured in whole blocks, so mip levels that are
+/// smaller than a block still take up a full block.
+fn texture_device_size(descriptor: &TextureDescriptor) -> usize {
+    let format_info = descriptor.format.describe();
+    let (block_width, block_height) = format_info.block_dimensions;
+    let block_size = format_info.block_size as u64;
+
+    let is_3d = descriptor.dimension == TextureDimension::D3;
+    let array_layers = if is_3d {
+        1
+    } else {
+        descriptor.size.depth_or_array_layers as u64
+    };
+
+    let bytes_per_layer: u64 = (0..descriptor.mip_level_count)
+        .map(|level| {
+            let size = descriptor.size.mip_level_size(level, is_3d);
+
+            let blocks_wide = block_count(size.width, block_width);
+            let blocks_high = block_count(size.height, block_height);
+            let depth = if is_3d {
+                size.depth_or_array_layers as u64
+            } else {
+                1
+            };
+
+            blocks_wide * blocks_high * depth * block_size
+        })
+        .sum();
+
+    (bytes_per_layer * array_layers * descriptor.sample_count as u64) as usize
+}
+
+/// Returns the number of blocks needed to cover `texels`, rounding up.
+#[inline]
+fn block_count(texels: u32, block_dimension: u8) -> u64 {
+    let texels = texels as u64;
+    let block_dimension = block_dimension as u64;
+
+    texels / block_dimension + u64::from(texels % block_dimension != 0)
+}
+
+/***************************************************************************************************
+
+                             dMMMMMMP dMMMMMP .dMMMb dMMMMMMP .dMMMb
+                               dMP   dMP     dMP" VP   dMP   dMP" VP
+                              dMP   dMMMP    VMMMb    dMP    VMMMb
+                             dMP   dMP     dP .dMP   dMP   dP .dMP
+                            dMP   dMMMMMP  VMMMP"   dMP    VMMMP"
+
+***************************************************************************************************/
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use bevy::render::render_resource::{Extent3d, TextureFormat, TextureUsages};
+
+    fn descriptor(
+        size: Extent3d,
+        mip_level_count: u32,
+        dimension: TextureDimension,
+        format: TextureFormat,
+    ) -> TextureDescriptor<'static> {
+        TextureDescriptor {
+            label: None,
+            size,
+            mip_level_count,
+            sample_count: 1,
+            dimension,
+            format,
+            usage: TextureUsages::TEXTURE_BINDING,
+        }
+    }
+
+    fn extent(width: u32, height: u32, depth_or_array_layers: u32) -> Extent3d {
+        Extent3d {
+            width,
+            height,
+            depth_or_array_layers,
+        }
+    }
+
+    #[test]
+    fn counts_bytes_per_pixel() {
+        let descriptor = descriptor(
+            extent(256, 128, 1),
+            1,
+            TextureDimension::D2,
+            TextureFormat::Rgba8UnormSrgb,
+        );
+
+        assert_eq!(texture_device_size(&descriptor), 256 * 128 * 4);
+    }
+
+    #[test]
+    fn counts_mip_levels_and_array_layers() {
+        let descriptor = descriptor(
+            extent(4, 4, 6),
+            3,
+            TextureDimension::D2,
+            TextureFormat::R8Unorm,
+        );
+
+        assert_eq!(texture_device_size(&descriptor), (16 + 4 + 1) * 6);
+    }
+
+    #[test]
+    fn counts_depth_of_3d_textures_per_mip_level() {
+        let descriptor = descriptor(
+            extent(4, 4, 4),
+            2,
+            TextureDimension::D3,
+            TextureFormat::R8Unorm,
+        );
+
+        assert_eq!(texture_device_size(&descriptor), 64 + 8);
+    }
+
+    #[test]
+    fn counts_whole_blocks_of_compressed_formats() {
+        // BC1 stores 4x4 blocks in 8 bytes.
+        let descriptor = descriptor(
+            extent(8, 8, 1),
+            3,
+            TextureDimension::D2,
+            TextureFormat::Bc1RgbaUnorm,
+        );
+
+        assert_eq!(texture_device_size(&descriptor), (4 + 1 + 1) * 8);
+    }
+}
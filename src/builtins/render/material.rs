@@ -1,9 +1,14 @@
 //! Memory usage tracking for Bevy's [`Image`] type.
 use bevy::{app::Plugin, pbr::StandardMaterial};
 
-use crate::{app_ext::RegisterTypesWithEstimator, estimator::ZeroEstimator};
+use crate::{
+    app_ext::RegisterTypesWithEstimator, estimator::ZeroEstimator, HandleReferences, HandleVisitor,
+};
 
 /// Adds memory tracking for [`StandardMaterial`] assets.
+///
+/// The textures of each material are followed by the deep estimates of
+/// [`MemoryUsage`][crate::MemoryUsage].
 #[derive(Debug, Default)]
 pub struct MaterialMemoryUsagePlugin;
 
@@ -11,5 +16,20 @@ impl Plugin for MaterialMemoryUsagePlugin {
     fn build(&self, app: &mut bevy::prelude::App) {
         // StandardMaterial has no heap usage.
         app.register_asset_with_estimator::<StandardMaterial, ZeroEstimator>();
+        app.register_asset_handle_references::<StandardMaterial, StandardMaterialReferences>();
+    }
+}
+
+/// Finds the textures of a [`StandardMaterial`].
+#[derive(Debug, Default)]
+struct StandardMaterialReferences;
+
+impl HandleReferences<StandardMaterial> for StandardMaterialReferences {
+    fn visit_handles(&self, material: &StandardMaterial, visitor: &mut HandleVisitor) {
+        visitor.visit_option(material.base_color_texture.as_ref());
+        visitor.visit_option(material.emissive_texture.as_ref());
+        visitor.visit_option(material.metallic_roughness_texture.as_ref());
+        visitor.visit_option(material.normal_map_texture.as_ref());
+        visitor.visit_option(material.occlusion_texture.as_ref());
     }
 }
@@ -1,20 +1,38 @@
 //! Memory usage tracking for Bevy's [`Mesh`] type.
-//!
-//! The behavior can be customized using the [`MemoryConfig`].
+
+use std::fmt;
 
 use bevy::{
-    app::Plugin,
-    render::mesh::{Mesh, VertexAttributeValues},
-    utils::HashSet,
+    app::{CoreStage, Plugin},
+    asset::{AssetEvent, Assets, HandleId},
+    ecs::{
+        event::EventReader,
+        system::{Commands, Local, Res, ResMut},
+    },
+    render::{
+        mesh::{Indices, Mesh, VertexAttributeValues},
+        render_resource::{VertexBufferLayout, VertexFormat},
+    },
 };
 
+use super::device::{
+    extract_device_sizes, register_prepared_asset_with_device_sizes, DeviceSizeChanges,
+};
 use crate::{
-    app_ext::RegisterTypesWithEstimator,
-    estimator::{FromConfig, ZeroEstimator},
-    DataSize, DataSizeEstimator, MemoryConfig, MemoryStats,
+    app_ext::{register_extracted_render_asset, RegisterTypesWithEstimator},
+    estimator::FromConfig,
+    systems::{is_tracking_enabled, CachedValue, InstanceCache, PersistentEstimator},
+    DataSize, DataSizeEstimator, MemoryConfig, MemoryStats, MemoryUsage,
 };
 
-/// Adds memory tracking for [`Mesh`] and [`GpuMesh`] assets.
+/// Adds memory tracking for [`Mesh`] and [`GpuMesh`] assets, and for the
+/// copies of changed meshes that are extracted to the render world.
+///
+/// The device memory of each [`GpuMesh`] is the size of its vertex and index
+/// buffers, computed from its [`Mesh`].
+///
+/// This also adds the [`MeshAttributeMemory`] resource, which breaks down the
+/// memory usage of all meshes by vertex attribute.
 ///
 /// [`GpuMesh`]: bevy::render::mesh::GpuMesh
 #[derive(Default)]
@@ -22,64 +40,469 @@ pub struct MeshMemoryUsagePlugin;
 
 impl Plugin for MeshMemoryUsagePlugin {
     fn build(&self, app: &mut bevy::prelude::App) {
-        // GpuMesh does not appear to have any heap storage.
-        app.register_render_asset_with_estimator::<Mesh, MeshSizeEstimator, ZeroEstimator>();
+        app.register_asset_with_estimator::<Mesh, MeshSizeEstimator>();
+
+        register_extracted_render_asset::<Mesh, MeshSizeEstimator>(app);
+        register_prepared_asset_with_device_sizes::<Mesh, _, _>(app, extract_mesh_device_sizes);
+
+        app.init_resource::<MeshAttributeMemory>();
+        app.add_system_to_stage(CoreStage::Last, update_mesh_attribute_memory);
     }
 }
 
-struct MeshSizeEstimator {
-    additional_vertex_attributes: Vec<&'static str>,
+/// Computes the size of the vertex and index buffers of each [`Mesh`] and
+/// sends them to the render world.
+///
+/// The buffers of a [`GpuMesh`] do not expose their size, so they are
+/// computed from the mesh's vertex layout and indices. They are only
+/// recomputed and sent again for meshes that changed.
+///
+/// [`GpuMesh`]: bevy::render::mesh::GpuMesh
+fn extract_mesh_device_sizes(
+    meshes: Res<Assets<Mesh>>,
+    mut mesh_events: EventReader<AssetEvent<Mesh>>,
+    mut changes: Local<DeviceSizeChanges>,
+    memory_config: Res<MemoryConfig>,
+    memory_usage: Res<MemoryUsage>,
+    mut render_commands: Commands,
+) {
+    extract_device_sizes(
+        &*meshes,
+        &mut mesh_events,
+        &mut *changes,
+        &*memory_config,
+        &*memory_usage,
+        &mut render_commands,
+        mesh_device_size,
+    );
+}
+
+/// Returns the combined size of the vertex and index buffers that are created
+/// for the given mesh when it is prepared.
+fn mesh_device_size(mesh: &Mesh) -> usize {
+    let index_buffer_size = mesh.get_index_buffer_bytes().map_or(0, <[u8]>::len);
+
+    vertex_data_size(mesh, &mesh.get_vertex_buffer_layout()) + index_buffer_size
+}
+
+/// Returns the number of bytes of vertex data stored in all of the mesh's
+/// attributes, given its vertex buffer layout.
+///
+/// [`Mesh::count_vertices`] panics if the attributes have different lengths,
+/// so the built-in attributes are measured one by one instead.
+fn vertex_data_size(mesh: &Mesh, layout: &VertexBufferLayout) -> usize {
+    let built_in_size: usize = BUILT_IN_ATTRIBUTES
+        .iter()
+        .filter_map(|name| mesh.attribute(*name))
+        .map(|values| values.len() * format_size(values))
+        .sum();
+
+    built_in_size + unnamed_attributes(mesh, layout, &BUILT_IN_ATTRIBUTES).total_heap_bytes
+}
+
+/// Estimates the [`MemoryStats`] of the vertex attributes of the mesh whose
+/// names are not in `names`.
+///
+/// `Mesh` cannot iterate through its attributes, so their formats are taken
+/// from its vertex buffer layout, and they are assumed to be as long as the
+/// longest attribute in `names`. If none of those are present, they are
+/// assumed to be as long as [`Mesh::count_vertices`], which only returns
+/// without panicking if all attributes have the same length, as they do in
+/// any mesh that can be rendered. Their heap size is based on their length,
+/// since their capacity is unknown.
+fn unnamed_attributes(mesh: &Mesh, layout: &VertexBufferLayout, names: &[&str]) -> MemoryStats {
+    let mut named_count = 0;
+    let mut named_stride = 0;
+    let mut vertex_count = None;
+
+    for values in names.iter().filter_map(|name| mesh.attribute(*name)) {
+        named_count += 1;
+        named_stride += format_size(values);
+        vertex_count = vertex_count.max(Some(values.len()));
+    }
+
+    let count = layout.attributes.len().saturating_sub(named_count);
+    if count == 0 {
+        return MemoryStats::default();
+    }
+
+    let vertex_count = vertex_count.unwrap_or_else(|| mesh.count_vertices());
+
+    let stride = (layout.array_stride as usize).saturating_sub(named_stride);
+
+    MemoryStats {
+        count,
+        total_stack_bytes: count * std::mem::size_of::<VertexAttributeValues>(),
+        total_heap_bytes: vertex_count * stride,
+        total_device_bytes: 0,
+    }
 }
 
-impl FromConfig for MeshSizeEstimator {
+/// Returns the number of bytes taken up by a single vertex of the given
+/// attribute.
+#[inline]
+fn format_size(values: &VertexAttributeValues) -> usize {
+    VertexFormat::from(values).size() as usize
+}
+
+/// The vertex attributes that are built into Bevy, which are reported
+/// individually by [`MeshAttributeMemory`].
+const BUILT_IN_ATTRIBUTES: [&str; 7] = [
+    Mesh::ATTRIBUTE_POSITION,
+    Mesh::ATTRIBUTE_NORMAL,
+    Mesh::ATTRIBUTE_UV_0,
+    Mesh::ATTRIBUTE_TANGENT,
+    Mesh::ATTRIBUTE_COLOR,
+    Mesh::ATTRIBUTE_JOINT_WEIGHT,
+    Mesh::ATTRIBUTE_JOINT_INDEX,
+];
+
+/// Configures the [`MeshAttributeMemory`] breakdown.
+///
+/// Insert this into [`MemoryConfig::estimator_configs`] to report custom
+/// vertex attributes individually.
+///
+/// # Example
+///
+/// ```
+/// # use bevy_datasize::{builtins::render::MeshAttributeConfig, MemoryConfig};
+/// let mut memory_config = MemoryConfig::default();
+/// memory_config.estimator_configs.insert(MeshAttributeConfig {
+///     custom_attributes: vec!["Vertex_Wind"],
+/// });
+/// ```
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MeshAttributeConfig {
+    /// The names of custom vertex attributes to report individually, in
+    /// addition to the built-in ones.
+    ///
+    /// All other custom attributes are summed up in
+    /// [`MeshAttributeMemory::custom_attributes`].
+    pub custom_attributes: Vec<&'static str>,
+}
+
+/// The names of the vertex attributes that are reported individually, which
+/// are kept until the [`MemoryConfig`] changes.
+struct MeshAttributeNames(Vec<&'static str>);
+
+impl FromConfig for MeshAttributeNames {
     fn from_config(config: &MemoryConfig) -> Self {
-        Self {
-            additional_vertex_attributes: Vec::from(&config.additional_mesh_vertex_attributes[..]),
+        let mut names = BUILT_IN_ATTRIBUTES.to_vec();
+
+        if let Some(config) = config.estimator_configs.get::<MeshAttributeConfig>() {
+            for name in config.custom_attributes.iter() {
+                if !names.contains(name) {
+                    names.push(name);
+                }
+            }
         }
+
+        Self(names)
     }
 }
 
-impl MeshSizeEstimator {
+/// The memory usage of all [`Mesh`] assets, broken down by vertex attribute.
+///
+/// This resource is added by the [`MeshMemoryUsagePlugin`] and is updated
+/// whenever the [`MemoryStats`] of [`Mesh`] are. Only the meshes that changed
+/// since the last update are measured again.
+///
+/// # Example
+///
+/// ```
+/// # use bevy::prelude::*;
+/// # use bevy_datasize::builtins::render::MeshAttributeMemory;
+/// fn print_tangent_usage(mesh_attributes: Res<MeshAttributeMemory>) {
+///     if let Some(tangents) = mesh_attributes.get(Mesh::ATTRIBUTE_TANGENT) {
+///         println!("Tangents: {tangents}");
+///     }
+/// }
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct MeshAttributeMemory {
+    attributes: Vec<(&'static str, MemoryStats)>,
+    custom_attributes: MemoryStats,
+    indices: MemoryStats,
+}
+
+impl MeshAttributeMemory {
+    fn from_totals(names: &[&'static str], totals: &MeshAttributeSizes) -> Self {
+        let mut attributes: Vec<_> = names
+            .iter()
+            .copied()
+            .zip(totals.named.iter().copied())
+            .filter(|(_, stats)| stats.count > 0)
+            .collect();
+
+        attributes
+            .sort_by(|(_, a), (_, b)| b.total_including_device().cmp(&a.total_including_device()));
+
+        Self {
+            attributes,
+            custom_attributes: totals.unnamed,
+            indices: totals.indices,
+        }
+    }
+
     #[cfg(test)]
-    fn new() -> Self {
+    fn from_meshes<'a, I>(names: &[&'static str], meshes: I) -> Self
+    where
+        I: IntoIterator<Item = &'a Mesh>,
+    {
+        let mut totals = MeshAttributeSizes::default();
+
+        for mesh in meshes {
+            MeshAttributeSizes::of(mesh, names).add_to(&mut totals);
+        }
+
+        Self::from_totals(names, &totals)
+    }
+
+    /// Returns the summed [`MemoryStats`] of each individually reported
+    /// vertex attribute that is present on at least one mesh, largest first.
+    ///
+    /// These are the built-in attributes and the ones listed in
+    /// [`MeshAttributeConfig::custom_attributes`]. The `count` of each
+    /// entry is the number of meshes with the attribute.
+    #[inline]
+    pub fn attributes(&self) -> &[(&'static str, MemoryStats)] {
+        &self.attributes
+    }
+
+    /// Returns the summed [`MemoryStats`] of the vertex attribute with the
+    /// given name, such as [`Mesh::ATTRIBUTE_NORMAL`].
+    ///
+    /// Only the individually reported attributes can be looked up. Use
+    /// [`custom_attributes`][Self::custom_attributes] for all others.
+    pub fn get(&self, name: &str) -> Option<MemoryStats> {
+        self.attributes
+            .iter()
+            .find(|(attribute, _)| *attribute == name)
+            .map(|(_, stats)| *stats)
+    }
+
+    /// Returns the summed [`MemoryStats`] of all custom vertex attributes
+    /// that are not reported individually.
+    ///
+    /// The `count` is the number of such attributes across all meshes. Their
+    /// heap size is estimated from their length, as their capacity is not
+    /// known.
+    #[inline]
+    pub fn custom_attributes(&self) -> MemoryStats {
+        self.custom_attributes
+    }
+
+    /// Returns the summed [`MemoryStats`] of the index lists of all meshes.
+    #[inline]
+    pub fn indices(&self) -> MemoryStats {
+        self.indices
+    }
+
+    /// Returns the sum of the [`MemoryStats`] of all vertex attributes and
+    /// index lists.
+    pub fn total(&self) -> MemoryStats {
+        self.attributes
+            .iter()
+            .map(|(_, stats)| *stats)
+            .sum::<MemoryStats>()
+            + self.custom_attributes
+            + self.indices
+    }
+}
+
+impl fmt::Display for MeshAttributeMemory {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Mesh memory usage by attribute:")?;
+
+        for (name, stats) in self.attributes.iter() {
+            writeln!(f, "{name}: {stats}")?;
+        }
+
+        writeln!(f, "Custom attributes: {}", self.custom_attributes)?;
+        writeln!(f, "Indices: {}", self.indices)?;
+
+        write!(f, "Total: {}", self.total())
+    }
+}
+
+/// The [`MemoryStats`] of the vertex attributes and indices of one or more
+/// meshes.
+#[derive(Debug, Clone, Default)]
+struct MeshAttributeSizes {
+    /// The stats of each individually reported attribute, in the order of
+    /// their names.
+    named: Vec<MemoryStats>,
+    unnamed: MemoryStats,
+    indices: MemoryStats,
+}
+
+impl MeshAttributeSizes {
+    fn of(mesh: &Mesh, names: &[&'static str]) -> Self {
+        let named = names
+            .iter()
+            .map(|name| {
+                mesh.attribute(*name)
+                    .map_or_else(MemoryStats::default, |values| {
+                        MemoryStats::from_value_with_estimator(
+                            values,
+                            &VertexAttributeSizeEstimator,
+                        )
+                    })
+            })
+            .collect();
+
+        let indices = mesh.indices().map_or_else(MemoryStats::default, |indices| {
+            MemoryStats::from_value_with_estimator(indices, &IndicesSizeEstimator)
+        });
+
+        let layout = mesh.get_vertex_buffer_layout();
+
         Self {
-            additional_vertex_attributes: Vec::new(),
+            named,
+            unnamed: unnamed_attributes(mesh, &layout, names),
+            indices,
+        }
+    }
+}
+
+impl CachedValue for MeshAttributeSizes {
+    fn add_to(&self, total: &mut Self) {
+        if total.named.len() < self.named.len() {
+            total.named.resize(self.named.len(), MemoryStats::default());
+        }
+
+        for (total, stats) in total.named.iter_mut().zip(self.named.iter()) {
+            *total = *total + *stats;
+        }
+
+        total.unnamed = total.unnamed + self.unnamed;
+        total.indices = total.indices + self.indices;
+    }
+
+    fn sub_from(&self, total: &mut Self) {
+        for (total, stats) in total.named.iter_mut().zip(self.named.iter()) {
+            *total = *total - *stats;
         }
+
+        total.unnamed = total.unnamed - self.unnamed;
+        total.indices = total.indices - self.indices;
+    }
+
+    /// Vertex attributes are never shared between meshes.
+    #[inline]
+    fn without_duplicates(&self, _duplicate_bytes: usize) -> Self {
+        self.clone()
     }
 }
 
+/// Updates the [`MeshAttributeMemory`] resource from the [`Mesh`] assets that
+/// changed since the last update.
+///
+/// All meshes are measured again after the [`MemoryConfig`] changes, in case
+/// the [`MeshAttributeConfig`] did.
+fn update_mesh_attribute_memory(
+    meshes: Res<Assets<Mesh>>,
+    mut mesh_events: EventReader<AssetEvent<Mesh>>,
+    mut names: Local<PersistentEstimator<MeshAttributeNames>>,
+    mut cache: Local<InstanceCache<HandleId, MeshAttributeSizes>>,
+    mut mesh_attributes: ResMut<MeshAttributeMemory>,
+    memory_config: Res<MemoryConfig>,
+    memory_usage: Res<MemoryUsage>,
+) {
+    if !is_tracking_enabled::<Mesh>(&*memory_config) {
+        // Neither the cache nor the breakdown would be kept up to date.
+        if cache.is_valid() {
+            cache.invalidate();
+            *mesh_attributes = MeshAttributeMemory::default();
+        }
+
+        return;
+    }
+
+    if names.update(&*memory_usage) {
+        cache.invalidate();
+    }
+
+    if cache.is_valid() {
+        for event in mesh_events.iter() {
+            match event {
+                AssetEvent::Created { handle } | AssetEvent::Modified { handle } => {
+                    cache.mark_changed(handle.id);
+                }
+                AssetEvent::Removed { handle } => {
+                    cache.remove(&handle.id);
+                }
+            }
+        }
+
+        if !memory_usage.is_sampling() {
+            return;
+        }
+
+        let names = &names.get(&*memory_config).0;
+        for handle_id in cache.take_changed() {
+            if let Some(mesh) = meshes.get(handle_id) {
+                cache.insert(handle_id, MeshAttributeSizes::of(mesh, names));
+            }
+        }
+    } else {
+        if !memory_usage.is_sampling() {
+            return;
+        }
+
+        // Any pending events are already reflected in the contents of
+        // `Assets<Mesh>`, so they can be skipped.
+        for _event in mesh_events.iter() {}
+
+        let names = &names.get(&*memory_config).0;
+        cache.rebuild(
+            meshes
+                .iter()
+                .map(|(handle_id, mesh)| (handle_id, MeshAttributeSizes::of(mesh, names))),
+        );
+    }
+
+    if cache.take_modified() {
+        let names = &names.get(&*memory_config).0;
+        *mesh_attributes = MeshAttributeMemory::from_totals(names, &cache.total());
+    }
+}
+
+#[derive(Debug, Default)]
+struct MeshSizeEstimator;
+
 impl DataSizeEstimator<Mesh> for MeshSizeEstimator {
     const IS_DYNAMIC: bool = true;
 
-    /// Sums up the sizes of the mesh's vertex attribute lists.
+    /// Sums up the sizes of all of the mesh's vertex attribute lists and of
+    /// its index list.
     fn estimate_heap_size(&self, mesh: &Mesh) -> usize {
-        const DEFAULT_ATTRIBUTES: [&str; 7] = [
-            Mesh::ATTRIBUTE_COLOR,
-            Mesh::ATTRIBUTE_NORMAL,
-            Mesh::ATTRIBUTE_TANGENT,
-            Mesh::ATTRIBUTE_POSITION,
-            Mesh::ATTRIBUTE_UV_0,
-            Mesh::ATTRIBUTE_JOINT_WEIGHT,
-            Mesh::ATTRIBUTE_JOINT_INDEX,
-        ];
-
-        let attributes: HashSet<&str> = DEFAULT_ATTRIBUTES
-            .into_iter()
-            .chain(self.additional_vertex_attributes.iter().copied())
-            .collect();
+        // `Mesh` has no method to iterate through its attributes, but its
+        // vertex buffer layout describes every one of them.
+        let layout = mesh.get_vertex_buffer_layout();
+        let attributes_stack_size =
+            layout.attributes.len() * std::mem::size_of::<VertexAttributeValues>();
+
+        let indices_heap_size = mesh.indices().map_or(0, |indices| {
+            MemoryStats::heap_size_of_with_estimator(indices, &IndicesSizeEstimator)
+        });
 
-        let total_size_of_attributes: usize = attributes
-            .into_iter()
-            .filter_map(|attribute_name| mesh.attribute(attribute_name))
-            .map(|attributes| {
-                MemoryStats::total_size_of_with_estimator(
-                    attributes,
-                    &VertexAttributeSizeEstimator::default(),
-                )
-            })
-            .sum();
+        attributes_stack_size + vertex_data_size(mesh, &layout) + indices_heap_size
+    }
+}
+
+#[derive(Debug, Default)]
+struct IndicesSizeEstimator;
 
-        total_size_of_attributes
+impl DataSizeEstimator<Indices> for IndicesSizeEstimator {
+    const IS_DYNAMIC: bool = true;
+
+    #[inline]
+    fn estimate_heap_size(&self, indices: &Indices) -> usize {
+        match indices {
+            Indices::U16(v) => v.estimate_heap_size(),
+            Indices::U32(v) => v.estimate_heap_size(),
+        }
     }
 }
 
@@ -161,6 +584,10 @@ mod tests {
         mesh
     }
 
+    fn vertex_data_size_of(mesh: &Mesh) -> usize {
+        vertex_data_size(mesh, &mesh.get_vertex_buffer_layout())
+    }
+
     #[test]
     fn counts_default_attributes() {
         let mesh = create_mesh(hashmap! {
@@ -168,14 +595,13 @@ mod tests {
             Mesh::ATTRIBUTE_NORMAL => 100,
         });
 
-        let estimator = MeshSizeEstimator::new();
-
-        let estimated_heap_size = MemoryStats::heap_size_of_with_estimator(&mesh, &estimator);
+        let estimated_heap_size =
+            MemoryStats::heap_size_of_with_estimator(&mesh, &MeshSizeEstimator);
         assert_eq!(estimated_heap_size, 200 + ATTRIBUTE_STACK_SIZE * 2);
     }
 
     #[test]
-    fn does_not_count_extra_attributes_if_not_configured() {
+    fn counts_custom_attributes() {
         let mesh = create_mesh(hashmap! {
             Mesh::ATTRIBUTE_POSITION => 100,
             Mesh::ATTRIBUTE_NORMAL => 100,
@@ -183,41 +609,176 @@ mod tests {
             "bar" => 100,
         });
 
-        let estimator = MeshSizeEstimator::new();
+        let estimated_heap_size =
+            MemoryStats::heap_size_of_with_estimator(&mesh, &MeshSizeEstimator);
+        assert_eq!(estimated_heap_size, 400 + ATTRIBUTE_STACK_SIZE * 4);
+    }
 
-        let estimated_heap_size = MemoryStats::heap_size_of_with_estimator(&mesh, &estimator);
-        assert_eq!(estimated_heap_size, 200 + ATTRIBUTE_STACK_SIZE * 2);
+    #[test]
+    fn counts_indices() {
+        let mut mesh = create_mesh(hashmap! {
+            Mesh::ATTRIBUTE_POSITION => 100,
+        });
+        mesh.set_indices(Some(Indices::U16(vec![0; 30])));
+
+        let estimated_heap_size =
+            MemoryStats::heap_size_of_with_estimator(&mesh, &MeshSizeEstimator);
+        assert_eq!(estimated_heap_size, 100 + ATTRIBUTE_STACK_SIZE + 60);
+
+        mesh.set_indices(Some(Indices::U32(vec![0; 30])));
+
+        let estimated_heap_size =
+            MemoryStats::heap_size_of_with_estimator(&mesh, &MeshSizeEstimator);
+        assert_eq!(estimated_heap_size, 100 + ATTRIBUTE_STACK_SIZE + 120);
     }
 
     #[test]
-    fn counts_extra_attributes_if_configured() {
-        let mesh = create_mesh(hashmap! {
+    fn counts_vertex_and_index_buffers_as_device_memory() {
+        let mut mesh = create_mesh(hashmap! {
             Mesh::ATTRIBUTE_POSITION => 100,
             Mesh::ATTRIBUTE_NORMAL => 100,
+        });
+        mesh.set_indices(Some(Indices::U32(vec![0; 30])));
+
+        assert_eq!(mesh_device_size(&mesh), 200 + 120);
+    }
+
+    #[test]
+    fn measures_attributes_with_different_lengths() {
+        let mut mesh = create_mesh(hashmap! {
+            Mesh::ATTRIBUTE_POSITION => 100,
+            Mesh::ATTRIBUTE_NORMAL => 60,
+            "foo" => 100,
+        });
+
+        // The custom attribute is assumed to be as long as the positions.
+        assert_eq!(vertex_data_size_of(&mesh), 100 + 60 + 100);
+
+        mesh.set_indices(Some(Indices::U16(vec![0; 30])));
+        assert_eq!(mesh_device_size(&mesh), 100 + 60 + 100 + 60);
+    }
+
+    #[test]
+    fn measures_meshes_with_only_custom_attributes() {
+        let mesh = create_mesh(hashmap! {
+            "foo" => 100,
+        });
+        assert_eq!(vertex_data_size_of(&mesh), 100);
+
+        let mesh = create_mesh(hashmap! {
             "foo" => 100,
             "bar" => 100,
         });
+        assert_eq!(vertex_data_size_of(&mesh), 200);
+        assert_eq!(
+            MemoryStats::heap_size_of_with_estimator(&mesh, &MeshSizeEstimator),
+            200 + ATTRIBUTE_STACK_SIZE * 2
+        );
+
+        let mesh = create_mesh(HashMap::new());
+        assert_eq!(vertex_data_size_of(&mesh), 0);
+    }
 
-        let estimator = MeshSizeEstimator {
-            additional_vertex_attributes: vec!["foo", "bar"],
-        };
+    #[test]
+    fn breaks_down_memory_by_attribute() {
+        let mut mesh = create_mesh(hashmap! {
+            Mesh::ATTRIBUTE_POSITION => 100,
+            Mesh::ATTRIBUTE_TANGENT => 100,
+            "foo" => 100,
+        });
+        mesh.set_indices(Some(Indices::U16(vec![0; 30])));
 
-        let estimated_heap_size = MemoryStats::heap_size_of_with_estimator(&mesh, &estimator);
-        assert_eq!(estimated_heap_size, 400 + ATTRIBUTE_STACK_SIZE * 4);
+        let other_mesh = create_mesh(hashmap! {
+            Mesh::ATTRIBUTE_POSITION => 100,
+        });
+
+        let breakdown =
+            MeshAttributeMemory::from_meshes(&BUILT_IN_ATTRIBUTES, [&mesh, &other_mesh]);
+
+        let positions = breakdown.get(Mesh::ATTRIBUTE_POSITION).unwrap();
+        assert_eq!(positions.count, 2);
+        assert_eq!(positions.total_heap_bytes, 200);
+
+        let tangents = breakdown.get(Mesh::ATTRIBUTE_TANGENT).unwrap();
+        assert_eq!(tangents.count, 1);
+        assert_eq!(tangents.total_heap_bytes, 100);
+
+        assert_eq!(breakdown.get(Mesh::ATTRIBUTE_NORMAL), None);
+
+        let custom_attributes = breakdown.custom_attributes();
+        assert_eq!(custom_attributes.count, 1);
+        assert_eq!(custom_attributes.total_heap_bytes, 100);
+
+        assert_eq!(breakdown.indices().total_heap_bytes, 60);
+        assert_eq!(breakdown.total().total_heap_bytes, 460);
     }
 
     #[test]
-    fn does_not_count_attribute_twice() {
+    fn breaks_down_listed_custom_attributes_individually() {
         let mesh = create_mesh(hashmap! {
-            Mesh::ATTRIBUTE_POSITION => 1000,
-            Mesh::ATTRIBUTE_NORMAL => 1000,
+            Mesh::ATTRIBUTE_POSITION => 100,
+            "foo" => 200,
+            "bar" => 100,
         });
 
-        let estimator = MeshSizeEstimator {
-            additional_vertex_attributes: vec![Mesh::ATTRIBUTE_POSITION],
-        };
+        let mut memory_config = MemoryConfig::default();
+        memory_config.estimator_configs.insert(MeshAttributeConfig {
+            custom_attributes: vec!["foo", Mesh::ATTRIBUTE_NORMAL],
+        });
+
+        let names = MeshAttributeNames::from_config(&memory_config).0;
+        assert_eq!(names.len(), BUILT_IN_ATTRIBUTES.len() + 1);
+
+        let breakdown = MeshAttributeMemory::from_meshes(&names, [&mesh]);
+
+        let foo = breakdown.get("foo").unwrap();
+        assert_eq!(foo.count, 1);
+        assert_eq!(foo.total_heap_bytes, 200);
+
+        assert_eq!(breakdown.attributes()[0].0, "foo");
+        assert_eq!(breakdown.get("bar"), None);
+
+        let custom_attributes = breakdown.custom_attributes();
+        assert_eq!(custom_attributes.count, 1);
+        assert_eq!(custom_attributes.total_heap_bytes, 100);
+
+        assert_eq!(breakdown.total().total_heap_bytes, 400);
+    }
+
+    #[test]
+    fn keeps_a_running_total_of_attribute_sizes() {
+        let mesh = create_mesh(hashmap! {
+            Mesh::ATTRIBUTE_POSITION => 100,
+            "foo" => 100,
+        });
+        let other_mesh = create_mesh(hashmap! {
+            Mesh::ATTRIBUTE_POSITION => 200,
+        });
 
-        let estimated_size = MemoryStats::total_size_of_with_estimator(&mesh, &estimator);
-        assert!(estimated_size < 3000);
+        let mut cache = InstanceCache::<u32, MeshAttributeSizes>::default();
+        cache.insert(0, MeshAttributeSizes::of(&mesh, &BUILT_IN_ATTRIBUTES));
+        cache.insert(1, MeshAttributeSizes::of(&other_mesh, &BUILT_IN_ATTRIBUTES));
+
+        let breakdown = MeshAttributeMemory::from_totals(&BUILT_IN_ATTRIBUTES, &cache.total());
+        assert_eq!(
+            breakdown
+                .get(Mesh::ATTRIBUTE_POSITION)
+                .unwrap()
+                .total_heap_bytes,
+            300
+        );
+        assert_eq!(breakdown.custom_attributes().total_heap_bytes, 100);
+
+        cache.remove(&0);
+
+        let breakdown = MeshAttributeMemory::from_totals(&BUILT_IN_ATTRIBUTES, &cache.total());
+        assert_eq!(
+            breakdown
+                .get(Mesh::ATTRIBUTE_POSITION)
+                .unwrap()
+                .total_heap_bytes,
+            200
+        );
+        assert_eq!(breakdown.custom_attributes(), MemoryStats::default());
     }
 }
@@ -2,6 +2,8 @@
 
 use bevy::app::{App, Plugin};
 
+#[cfg(any(feature = "image", feature = "mesh"))]
+mod device;
 #[cfg(feature = "image")]
 mod image;
 #[cfg(feature = "material")]
@@ -14,7 +16,7 @@ pub use image::ImageMemoryUsagePlugin;
 #[cfg(feature = "material")]
 pub use material::MaterialMemoryUsagePlugin;
 #[cfg(feature = "mesh")]
-pub use mesh::MeshMemoryUsagePlugin;
+pub use mesh::{MeshAttributeConfig, MeshAttributeMemory, MeshMemoryUsagePlugin};
 
 /// Adds memory tracking for the components, resources, and assets that are part
 /// of Bevy's [`RenderPlugin`][bevy::render::RenderPlugin].
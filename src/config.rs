@@ -1,24 +1,121 @@
+use std::{
+    any::{Any, TypeId},
+    fmt,
+    time::Duration,
+};
+
+use bevy::utils::{HashMap, HashSet};
+
+use crate::type_info::short_type_name;
+
 /// Configuration for the [`MemoryUsagePlugin`][crate::MemoryUsagePlugin].
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct MemoryConfig {
     /// Whether to track memory usage for all registered types.
+    ///
+    /// If this is `false`, no types are tracked, regardless of the per-type
+    /// settings below.
     pub global: bool,
 
-    /// A list of additional [`Mesh`] vertex attributes to track.
+    /// Types for which memory usage tracking is turned off.
+    ///
+    /// See also [`disable`][Self::disable] and [`enable`][Self::enable].
+    pub disabled_types: HashSet<TypeId>,
+
+    /// Names of types for which memory usage tracking is turned off.
+    ///
+    /// A type matches an entry in this set if the entry is equal to either its
+    /// full name as given by [`std::any::type_name`] (e.g.
+    /// `"bevy_render::texture::image::Image"`) or its name without module
+    /// paths (e.g. `"Image"`).
+    ///
+    /// See also [`disable_by_name`][Self::disable_by_name] and
+    /// [`enable_by_name`][Self::enable_by_name].
+    pub disabled_type_names: HashSet<String>,
+
+    /// How often the memory usage of registered types is measured.
+    ///
+    /// See [`SamplingPolicy`] for details.
+    pub sampling: SamplingPolicy,
+
+    /// The number of [`MemorySample`]s to keep for each registered type.
     ///
-    /// If the plugin is tracking meshes, then by default, it will only account
-    /// for data stored in [the "built-in" vertex attributes of `Mesh`].
+    /// A sample is recorded after every frame during which stats were updated
+    /// (see [`sampling`][Self::sampling]), and can be retrieved with
+    /// [`MemoryUsage::get_history`].
     ///
-    /// If your meshes have any custom attributes, you'll need to specify them
-    /// here in order for their memory usage to be tracked.
+    /// If this is `0`, no history is kept.
+    ///
+    /// [`MemorySample`]: crate::MemorySample
+    /// [`MemoryUsage::get_history`]: crate::MemoryUsage::get_history
+    pub history_length: usize,
+
+    /// Whether to compute a [`SizeDistribution`] of the individual instances of
+    /// each registered type when its stats are updated.
+    ///
+    /// The distributions can be retrieved with
+    /// [`MemoryUsage::get_distribution`].
+    ///
+    /// This requires keeping the size of every instance around while
+    /// measuring, and sorting them, so it is off by default.
+    ///
+    /// [`SizeDistribution`]: crate::SizeDistribution
+    /// [`MemoryUsage::get_distribution`]: crate::MemoryUsage::get_distribution
+    pub size_distributions: bool,
+
+    /// The number of largest instances to keep track of for each registered
+    /// component, asset, and render asset type.
+    ///
+    /// The instances are identified by their [`Entity`] or [`HandleId`], and
+    /// can be retrieved with [`MemoryUsage::get_largest_instances`].
+    ///
+    /// If this is `0`, no instances are tracked.
+    ///
+    /// [`Entity`]: bevy::ecs::entity::Entity
+    /// [`HandleId`]: bevy::asset::HandleId
+    /// [`MemoryUsage::get_largest_instances`]: crate::MemoryUsage::get_largest_instances
+    pub largest_instances: usize,
+
+    /// Whether to count each shared allocation, such as the payload of an
+    /// [`Arc`], only once per registered type when its stats are updated.
     ///
-    /// This is due to the fact that, currently, [`Mesh`] has no method to
-    /// iterate through its attributes.
+    /// Shared allocations must be reported by the [`DataSizeEstimator`] of
+    /// the type (see [`ArcEstimator`]). Types that implement [`DataSize`] can
+    /// report them through [`VisitSharedAllocations`] and be registered with
+    /// [`SharedForwardingEstimator`]. The size of the distinct shared
+    /// allocations can be retrieved with [`MemoryUsage::get_shared_bytes`].
     ///
-    /// [`Mesh`]: bevy::render::mesh::Mesh
-    /// [the "built-in" vertex attributes of `Mesh`]:
-    ///     bevy::render::mesh::Mesh#associatedconstant.ATTRIBUTE_COLOR
-    pub additional_mesh_vertex_attributes: Vec<&'static str>,
+    /// Deduplication happens across all instances of a type, including the
+    /// estimates that are cached per instance for assets and for components
+    /// with [`ComponentTrackingMode::Incremental`].
+    ///
+    /// Only the totals of each type are deduplicated. The stats of each
+    /// instance, as used for [`size_distributions`][Self::size_distributions]
+    /// and [`largest_instances`][Self::largest_instances], include the full
+    /// size of every shared allocation that it holds, as if it were the only
+    /// holder. This keeps them independent of the order in which instances
+    /// are measured and of the [`ComponentTrackingMode`].
+    ///
+    /// [`DataSize`]: crate::DataSize
+    /// [`VisitSharedAllocations`]: crate::VisitSharedAllocations
+    /// [`SharedForwardingEstimator`]: crate::estimator::SharedForwardingEstimator
+    /// [`Arc`]: std::sync::Arc
+    /// [`DataSizeEstimator`]: crate::DataSizeEstimator
+    /// [`ArcEstimator`]: crate::estimator::ArcEstimator
+    /// [`MemoryUsage::get_shared_bytes`]: crate::MemoryUsage::get_shared_bytes
+    pub deduplicate_shared_allocations: bool,
+
+    /// How the memory usage of registered components is measured.
+    ///
+    /// See [`ComponentTrackingMode`] for details.
+    pub component_tracking: ComponentTrackingMode,
+
+    /// Settings for individual [`DataSizeEstimator`]s, keyed by their type.
+    ///
+    /// See [`EstimatorConfigs`] for details.
+    ///
+    /// [`DataSizeEstimator`]: crate::DataSizeEstimator
+    pub estimator_configs: EstimatorConfigs,
 }
 
 impl MemoryConfig {
@@ -30,13 +127,510 @@ impl MemoryConfig {
             ..Default::default()
         }
     }
+
+    /// Returns `true` if memory usage should be tracked for the given type.
+    pub fn is_enabled<T>(&self) -> bool
+    where
+        T: Any,
+    {
+        if !self.global {
+            return false;
+        }
+
+        if self.disabled_types.contains(&TypeId::of::<T>()) {
+            return false;
+        }
+
+        if !self.disabled_type_names.is_empty() {
+            let type_name = std::any::type_name::<T>();
+
+            if self.disabled_type_names.contains(type_name)
+                || self
+                    .disabled_type_names
+                    .contains(&short_type_name(type_name))
+            {
+                return false;
+            }
+        }
+
+        true
+    }
+
+    /// Turns off memory usage tracking for the given type.
+    pub fn disable<T>(&mut self)
+    where
+        T: Any,
+    {
+        self.disabled_types.insert(TypeId::of::<T>());
+    }
+
+    /// Turns memory usage tracking for the given type back on after a call to
+    /// [`disable`][Self::disable].
+    ///
+    /// This has no effect if the type is disabled by name.
+    pub fn enable<T>(&mut self)
+    where
+        T: Any,
+    {
+        self.disabled_types.remove(&TypeId::of::<T>());
+    }
+
+    /// Turns off memory usage tracking for the type(s) with the given name.
+    ///
+    /// See [`disabled_type_names`][Self::disabled_type_names] for how names
+    /// are matched.
+    pub fn disable_by_name(&mut self, type_name: impl Into<String>) {
+        self.disabled_type_names.insert(type_name.into());
+    }
+
+    /// Turns memory usage tracking back on after a call to
+    /// [`disable_by_name`][Self::disable_by_name].
+    pub fn enable_by_name(&mut self, type_name: &str) {
+        self.disabled_type_names.remove(type_name);
+    }
 }
 
 impl Default for MemoryConfig {
     fn default() -> Self {
         Self {
             global: true,
-            additional_mesh_vertex_attributes: Default::default(),
+            disabled_types: Default::default(),
+            disabled_type_names: Default::default(),
+            sampling: Default::default(),
+            history_length: 0,
+            size_distributions: false,
+            largest_instances: 0,
+            deduplicate_shared_allocations: false,
+            component_tracking: Default::default(),
+            estimator_configs: Default::default(),
+        }
+    }
+}
+
+/// A set of configuration values for [`DataSizeEstimator`]s, holding at most
+/// one value of each type.
+///
+/// This gives estimators, including those from third-party crates, a place to
+/// store their settings in the [`MemoryConfig`]. Implementations of
+/// [`FromConfig`] can read their own config type from
+/// [`MemoryConfig::estimator_configs`].
+///
+/// [`FromConfig`] is implemented for every type that implements [`Default`],
+/// by ignoring the config. An estimator that reads its config has to
+/// implement [`FromConfig`] itself, so it cannot implement [`Default`].
+///
+/// Estimators are only recreated when the [`MemoryConfig`] changes, so
+/// changes to the values in here take effect on the next update.
+///
+/// # Example
+///
+/// ```
+/// # use bevy_datasize::{estimator::FromConfig, DataSizeEstimator, MemoryConfig};
+/// struct Buffer {
+///     len: usize,
+/// }
+///
+/// #[derive(Debug, Clone, PartialEq)]
+/// struct BufferEstimatorConfig {
+///     bytes_per_element: usize,
+/// }
+///
+/// struct BufferEstimator {
+///     bytes_per_element: usize,
+/// }
+///
+/// impl FromConfig for BufferEstimator {
+///     fn from_config(config: &MemoryConfig) -> Self {
+///         let bytes_per_element = config
+///             .estimator_configs
+///             .get::<BufferEstimatorConfig>()
+///             .map_or(4, |config| config.bytes_per_element);
+///
+///         Self { bytes_per_element }
+///     }
+/// }
+///
+/// impl DataSizeEstimator<Buffer> for BufferEstimator {
+///     const IS_DYNAMIC: bool = true;
+///
+///     fn estimate_heap_size(&self, buffer: &Buffer) -> usize {
+///         buffer.len * self.bytes_per_element
+///     }
+/// }
+///
+/// let mut config = MemoryConfig::default();
+/// config.estimator_configs.insert(BufferEstimatorConfig {
+///     bytes_per_element: 16,
+/// });
+///
+/// let estimator = BufferEstimator::from_config(&config);
+/// assert_eq!(estimator.estimate_heap_size(&Buffer { len: 10 }), 160);
+/// ```
+///
+/// [`DataSizeEstimator`]: crate::DataSizeEstimator
+/// [`FromConfig`]: crate::estimator::FromConfig
+#[derive(Default)]
+pub struct EstimatorConfigs {
+    configs: HashMap<TypeId, Box<dyn EstimatorConfig>>,
+}
+
+impl EstimatorConfigs {
+    /// Inserts the given config value, returning the previous value of the
+    /// same type, if any.
+    pub fn insert<C>(&mut self, config: C) -> Option<C>
+    where
+        C: EstimatorConfigValue,
+    {
+        let old_config = self.configs.insert(TypeId::of::<C>(), Box::new(config))?;
+
+        old_config.into_any().downcast().ok().map(|config| *config)
+    }
+
+    /// Returns the config value of the given type, if any.
+    pub fn get<C>(&self) -> Option<&C>
+    where
+        C: EstimatorConfigValue,
+    {
+        self.configs
+            .get(&TypeId::of::<C>())
+            .and_then(|config| config.as_any().downcast_ref())
+    }
+
+    /// Returns a mutable reference to the config value of the given type, if
+    /// any.
+    pub fn get_mut<C>(&mut self) -> Option<&mut C>
+    where
+        C: EstimatorConfigValue,
+    {
+        self.configs
+            .get_mut(&TypeId::of::<C>())
+            .and_then(|config| config.as_any_mut().downcast_mut())
+    }
+
+    /// Returns the config value of the given type, or its default value if
+    /// there is none.
+    pub fn get_or_default<C>(&self) -> C
+    where
+        C: EstimatorConfigValue + Default,
+    {
+        self.get::<C>().cloned().unwrap_or_default()
+    }
+
+    /// Removes the config value of the given type and returns it, if any.
+    pub fn remove<C>(&mut self) -> Option<C>
+    where
+        C: EstimatorConfigValue,
+    {
+        let config = self.configs.remove(&TypeId::of::<C>())?;
+
+        config.into_any().downcast().ok().map(|config| *config)
+    }
+
+    /// Returns `true` if there is a config value of the given type.
+    pub fn contains<C>(&self) -> bool
+    where
+        C: EstimatorConfigValue,
+    {
+        self.configs.contains_key(&TypeId::of::<C>())
+    }
+
+    /// Returns the number of config values.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.configs.len()
+    }
+
+    /// Returns `true` if there are no config values.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.configs.is_empty()
+    }
+}
+
+impl Clone for EstimatorConfigs {
+    fn clone(&self) -> Self {
+        Self {
+            configs: self
+                .configs
+                .iter()
+                .map(|(type_id, config)| (*type_id, config.clone_boxed()))
+                .collect(),
         }
     }
 }
+
+impl PartialEq for EstimatorConfigs {
+    fn eq(&self, other: &Self) -> bool {
+        self.configs.len() == other.configs.len()
+            && self.configs.iter().all(|(type_id, config)| {
+                matches!(
+                    other.configs.get(type_id),
+                    Some(other_config) if config.eq_dyn(&**other_config)
+                )
+            })
+    }
+}
+
+impl fmt::Debug for EstimatorConfigs {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_set().entries(self.configs.values()).finish()
+    }
+}
+
+/// The requirements for a type to be stored in [`EstimatorConfigs`].
+///
+/// This is implemented for all types that meet them. They allow the
+/// [`MemoryConfig`] to be cloned, compared, and sent to the render world.
+pub trait EstimatorConfigValue: Any + Clone + PartialEq + fmt::Debug + Send + Sync {}
+
+impl<T> EstimatorConfigValue for T where T: Any + Clone + PartialEq + fmt::Debug + Send + Sync {}
+
+/// Object-safe form of [`EstimatorConfigValue`].
+trait EstimatorConfig: Any + fmt::Debug + Send + Sync {
+    fn clone_boxed(&self) -> Box<dyn EstimatorConfig>;
+
+    fn eq_dyn(&self, other: &dyn EstimatorConfig) -> bool;
+
+    fn as_any(&self) -> &dyn Any;
+
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+
+    fn into_any(self: Box<Self>) -> Box<dyn Any>;
+}
+
+impl<C> EstimatorConfig for C
+where
+    C: EstimatorConfigValue,
+{
+    fn clone_boxed(&self) -> Box<dyn EstimatorConfig> {
+        Box::new(self.clone())
+    }
+
+    fn eq_dyn(&self, other: &dyn EstimatorConfig) -> bool {
+        other.as_any().downcast_ref::<C>() == Some(self)
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+
+    fn into_any(self: Box<Self>) -> Box<dyn Any> {
+        self
+    }
+}
+
+/// Determines how the [`MemoryStats`] of registered [`Component`] types are
+/// computed.
+///
+/// [`MemoryStats`]: crate::MemoryStats
+/// [`Component`]: bevy::ecs::component::Component
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ComponentTrackingMode {
+    /// Every instance of the component is re-estimated on every update.
+    Full,
+
+    /// The estimate for each entity is cached, and only entities whose
+    /// component was added or changed since the last update are re-estimated.
+    ///
+    /// Entities whose component was removed have their cached estimate
+    /// subtracted from the totals.
+    ///
+    /// This trades a small amount of memory per entity for a much cheaper
+    /// update when most components do not change from frame to frame.
+    ///
+    /// Unlike with [`Full`][Self::Full], the stats are updated in
+    /// [`CoreStage::Last`] rather than [`CoreStage::Update`], so that
+    /// components removed by commands are seen in the frame they are removed.
+    /// Systems that read the stats during [`CoreStage::Update`] see the values
+    /// from the previous frame.
+    ///
+    /// [`CoreStage::Last`]: bevy::app::CoreStage::Last
+    /// [`CoreStage::Update`]: bevy::app::CoreStage::Update
+    Incremental,
+}
+
+impl Default for ComponentTrackingMode {
+    fn default() -> Self {
+        Self::Full
+    }
+}
+
+/// Determines how often the [`MemoryStats`] of registered types are updated.
+///
+/// [`MemoryStats`]: crate::MemoryStats
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SamplingPolicy {
+    /// Stats are updated every frame.
+    EveryFrame,
+
+    /// Stats are updated once every `N` frames.
+    EveryNFrames(u32),
+
+    /// Stats are updated at most once per the given duration of wall-clock
+    /// time.
+    Interval(Duration),
+
+    /// Stats are only updated when requested with
+    /// [`MemoryUsage::request_sample`].
+    ///
+    /// [`MemoryUsage::request_sample`]: crate::MemoryUsage::request_sample
+    OnDemand,
+}
+
+impl Default for SamplingPolicy {
+    fn default() -> Self {
+        Self::EveryFrame
+    }
+}
+
+/***************************************************************************************************
+
+                             dMMMMMMP dMMMMMP .dMMMb dMMMMMMP .dMMMb
+                               dMP   dMP     dMP" VP   dMP   dMP" VP
+                              dMP   dMMMP    VMMMb    dMP    VMMMb
+                             dMP   dMP     dP .dMP   dMP   dP .dMP
+                            dMP   dMMMMMP  VMMMP"   dMP    VMMMP"
+
+***************************************************************************************************/
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, Clone, Default, PartialEq)]
+    struct BytesPerElement(usize);
+
+    #[derive(Debug, Clone, Default, PartialEq)]
+    struct Scale(f32);
+
+    #[test]
+    fn holds_one_value_per_type() {
+        let mut configs = EstimatorConfigs::default();
+        assert!(configs.is_empty());
+
+        assert_eq!(configs.insert(BytesPerElement(4)), None);
+        assert_eq!(configs.insert(Scale(0.5)), None);
+        assert_eq!(configs.insert(BytesPerElement(8)), Some(BytesPerElement(4)));
+        assert_eq!(configs.len(), 2);
+
+        assert_eq!(configs.get::<BytesPerElement>(), Some(&BytesPerElement(8)));
+        assert_eq!(configs.get::<Scale>(), Some(&Scale(0.5)));
+        assert_eq!(configs.get::<String>(), None);
+    }
+
+    #[test]
+    fn modifies_and_removes_values() {
+        let mut configs = EstimatorConfigs::default();
+        configs.insert(BytesPerElement(4));
+
+        configs.get_mut::<BytesPerElement>().unwrap().0 = 16;
+        assert_eq!(configs.get::<BytesPerElement>(), Some(&BytesPerElement(16)));
+        assert_eq!(configs.get_mut::<Scale>(), None);
+
+        assert_eq!(
+            configs.get_or_default::<BytesPerElement>(),
+            BytesPerElement(16)
+        );
+        assert_eq!(configs.get_or_default::<Scale>(), Scale(0.0));
+
+        assert!(configs.contains::<BytesPerElement>());
+        assert_eq!(
+            configs.remove::<BytesPerElement>(),
+            Some(BytesPerElement(16))
+        );
+        assert!(!configs.contains::<BytesPerElement>());
+        assert_eq!(configs.remove::<BytesPerElement>(), None);
+        assert!(configs.is_empty());
+    }
+
+    #[test]
+    fn clones_and_compares_values() {
+        let mut configs = EstimatorConfigs::default();
+        configs.insert(BytesPerElement(4));
+        configs.insert(Scale(0.5));
+
+        let mut cloned = configs.clone();
+        assert_eq!(cloned, configs);
+        assert_eq!(cloned.get::<Scale>(), Some(&Scale(0.5)));
+
+        cloned.insert(Scale(2.0));
+        assert_ne!(cloned, configs);
+        assert_eq!(configs.get::<Scale>(), Some(&Scale(0.5)));
+
+        cloned.remove::<Scale>();
+        assert_ne!(cloned, configs);
+
+        let mut other = EstimatorConfigs::default();
+        other.insert(BytesPerElement(4));
+        assert_eq!(cloned, other);
+
+        other.insert(String::from("unrelated"));
+        assert_ne!(cloned, other);
+    }
+
+    #[test]
+    fn config_changes_with_estimator_configs() {
+        let config = MemoryConfig::default();
+
+        let mut other_config = config.clone();
+        assert_eq!(other_config, config);
+
+        other_config.estimator_configs.insert(Scale(0.5));
+        assert_ne!(other_config, config);
+    }
+
+    #[test]
+    fn disables_types_by_full_or_short_name() {
+        let mut config = MemoryConfig::default();
+        assert!(config.is_enabled::<BytesPerElement>());
+
+        config.disable_by_name(std::any::type_name::<BytesPerElement>());
+        assert!(!config.is_enabled::<BytesPerElement>());
+        assert!(config.is_enabled::<Scale>());
+
+        config.enable_by_name(std::any::type_name::<BytesPerElement>());
+        assert!(config.is_enabled::<BytesPerElement>());
+
+        config.disable_by_name("BytesPerElement");
+        assert!(!config.is_enabled::<BytesPerElement>());
+        assert!(config.is_enabled::<Scale>());
+
+        // A partial module path matches neither the full nor the short name.
+        let mut config = MemoryConfig::default();
+        config.disable_by_name("tests::BytesPerElement");
+        assert!(config.is_enabled::<BytesPerElement>());
+    }
+
+    #[test]
+    fn enable_does_not_override_disabling_by_name() {
+        let mut config = MemoryConfig::default();
+        config.disable::<BytesPerElement>();
+        config.disable_by_name("BytesPerElement");
+
+        config.enable::<BytesPerElement>();
+        assert!(!config.is_enabled::<BytesPerElement>());
+
+        config.enable_by_name("BytesPerElement");
+        assert!(config.is_enabled::<BytesPerElement>());
+    }
+
+    #[test]
+    fn global_switch_overrides_per_type_settings() {
+        let mut config = MemoryConfig::disabled_at_start();
+        assert!(!config.is_enabled::<BytesPerElement>());
+
+        config.enable::<BytesPerElement>();
+        assert!(!config.is_enabled::<BytesPerElement>());
+
+        config.global = true;
+        assert!(config.is_enabled::<BytesPerElement>());
+
+        config.disable::<BytesPerElement>();
+        assert!(!config.is_enabled::<BytesPerElement>());
+        assert!(config.is_enabled::<Scale>());
+    }
+}
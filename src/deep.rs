@@ -0,0 +1,611 @@
+use std::{
+    any::{Any, TypeId},
+    fmt,
+};
+
+use bevy::{
+    asset::{Asset, Assets, Handle, HandleId},
+    ecs::{component::Component, entity::Entity, world::World},
+};
+
+use crate::{
+    entity::{persistent_estimator, EstimatorState},
+    estimator::FromConfig,
+    MemoryConfig, MemoryStats, TypeInfo,
+};
+
+/// Indicates that a type can find the asset [`Handle`]s that are held by
+/// values of type `T`.
+///
+/// Like [`DataSizeEstimator`], this is implemented on a separate type so that
+/// it can be implemented for types from the Bevy crate(s).
+///
+/// # Example
+///
+/// ```
+/// # use bevy::prelude::*;
+/// # use bevy_datasize::{HandleReferences, HandleVisitor};
+/// #[derive(Component)]
+/// struct Decal {
+///     texture: Handle<Image>,
+///     mask: Option<Handle<Image>>,
+/// }
+///
+/// #[derive(Default)]
+/// struct DecalReferences;
+///
+/// impl HandleReferences<Decal> for DecalReferences {
+///     fn visit_handles(&self, decal: &Decal, visitor: &mut HandleVisitor) {
+///         visitor.visit(&decal.texture);
+///         visitor.visit_option(decal.mask.as_ref());
+///     }
+/// }
+/// ```
+///
+/// [`DataSizeEstimator`]: crate::DataSizeEstimator
+pub trait HandleReferences<T: ?Sized> {
+    /// Passes each asset [`Handle`] held by the given value to the visitor.
+    fn visit_handles(&self, value: &T, visitor: &mut HandleVisitor);
+}
+
+/// Collects the asset [`Handle`]s that are held by a value.
+///
+/// See [`HandleReferences`].
+#[derive(Debug, Default)]
+pub struct HandleVisitor {
+    handles: Vec<(TypeId, HandleId)>,
+}
+
+impl HandleVisitor {
+    /// Records a reference to the asset with the given handle.
+    #[inline]
+    pub fn visit<A>(&mut self, handle: &Handle<A>)
+    where
+        A: Asset,
+    {
+        self.handles.push((TypeId::of::<A>(), handle.id));
+    }
+
+    /// Records a reference to the asset with the given handle, if any.
+    #[inline]
+    pub fn visit_option<A>(&mut self, handle: Option<&Handle<A>>)
+    where
+        A: Asset,
+    {
+        if let Some(handle) = handle {
+            self.visit(handle);
+        }
+    }
+}
+
+/// Determines how the size of an asset is attributed to the values that
+/// reference it in a deep estimate.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum HandleAttribution {
+    /// Every value that references an asset is attributed its full size.
+    ///
+    /// This answers "how much memory would this value keep alive on its own".
+    Full,
+
+    /// The size of an asset is split evenly among all of the entities and
+    /// assets that reference it.
+    ///
+    /// An asset that is reached both directly and through other assets is
+    /// attributed a share along each path. Summing the proportional estimates
+    /// of all referrers of an asset gives the size of the asset, up to
+    /// rounding. The same goes for the instance counts.
+    Proportional,
+}
+
+impl Default for HandleAttribution {
+    fn default() -> Self {
+        Self::Full
+    }
+}
+
+/// The estimated memory usage of a value together with the assets that it
+/// references, directly or through other assets.
+///
+/// Use [`MemoryUsage::measure_entity_deep`] or
+/// [`MemoryUsage::measure_asset_deep`] to create one.
+///
+/// [`MemoryUsage::measure_entity_deep`]: crate::MemoryUsage::measure_entity_deep
+/// [`MemoryUsage::measure_asset_deep`]: crate::MemoryUsage::measure_asset_deep
+#[derive(Debug, Clone)]
+pub struct DeepMemory {
+    shallow: MemoryStats,
+    referenced: Vec<(TypeInfo, MemoryStats)>,
+}
+
+impl DeepMemory {
+    pub(crate) fn new(shallow: MemoryStats, mut referenced: Vec<(TypeInfo, MemoryStats)>) -> Self {
+        referenced.sort_by(|(a_info, a), (b_info, b)| {
+            b.total_including_device()
+                .cmp(&a.total_including_device())
+                .then_with(|| a_info.short_name.cmp(&b_info.short_name))
+        });
+
+        Self {
+            shallow,
+            referenced,
+        }
+    }
+
+    /// Returns the [`MemoryStats`] of the value itself, without the assets
+    /// that it references.
+    #[inline]
+    pub fn shallow(&self) -> MemoryStats {
+        self.shallow
+    }
+
+    /// Returns the [`MemoryStats`] attributed to the value for each type of
+    /// referenced asset, largest first.
+    #[inline]
+    pub fn referenced(&self) -> &[(TypeInfo, MemoryStats)] {
+        &self.referenced
+    }
+
+    /// Returns the [`MemoryStats`] attributed to the value for the referenced
+    /// assets of the given type.
+    pub fn get<T>(&self) -> Option<MemoryStats>
+    where
+        T: Any,
+    {
+        let type_id = TypeId::of::<T>();
+
+        self.referenced
+            .iter()
+            .find(|(info, _)| info.type_id == type_id)
+            .map(|(_, stats)| *stats)
+    }
+
+    /// Returns the sum of the [`MemoryStats`] attributed to the value for all
+    /// of the assets it references.
+    pub fn total_referenced(&self) -> MemoryStats {
+        self.referenced.iter().map(|(_, stats)| *stats).sum()
+    }
+
+    /// Returns the sum of the shallow and referenced [`MemoryStats`].
+    pub fn total(&self) -> MemoryStats {
+        self.shallow + self.total_referenced()
+    }
+}
+
+impl fmt::Display for DeepMemory {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Shallow: {}", self.shallow)?;
+
+        for (info, stats) in self.referenced.iter() {
+            writeln!(f, "{}: {stats}", info.short_name)?;
+        }
+
+        write!(f, "Total: {}", self.total())
+    }
+}
+
+/// Returns the handles held by a single component on an entity.
+///
+/// Like a [`ComponentEstimatorFn`], this keeps the [`HandleReferences`] in the
+/// given [`EstimatorState`] between calls.
+///
+/// [`ComponentEstimatorFn`]: crate::entity::ComponentEstimatorFn
+pub(crate) type ComponentReferencesFn =
+    fn(&World, Entity, &MemoryConfig, u64, &mut EstimatorState) -> Option<Vec<(TypeId, HandleId)>>;
+
+/// A [`ComponentReferencesFn`] that uses the given [`HandleReferences`] type.
+pub(crate) fn component_references<T, R>(
+    world: &World,
+    entity: Entity,
+    memory_config: &MemoryConfig,
+    config_generation: u64,
+    state: &mut EstimatorState,
+) -> Option<Vec<(TypeId, HandleId)>>
+where
+    T: Any + Component,
+    R: HandleReferences<T> + FromConfig + Send + 'static,
+{
+    let component = world.get::<T>(entity)?;
+    let references = persistent_estimator::<R>(state, memory_config, config_generation);
+
+    Some(visit_handles(component, references))
+}
+
+/// Type-erased functions for finding the handles held by the assets of a
+/// single type.
+///
+/// Both keep the [`HandleReferences`] in the given [`EstimatorState`] like a
+/// [`ComponentReferencesFn`].
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct AssetReferencesFns {
+    /// Returns the handles held by the asset with the given [`HandleId`].
+    pub(crate) visit: fn(
+        &World,
+        HandleId,
+        &MemoryConfig,
+        u64,
+        &mut EstimatorState,
+    ) -> Option<Vec<(TypeId, HandleId)>>,
+
+    /// Returns the handles held by each asset of the type.
+    pub(crate) visit_all:
+        fn(&World, &MemoryConfig, u64, &mut EstimatorState) -> Vec<Vec<(TypeId, HandleId)>>,
+}
+
+impl AssetReferencesFns {
+    /// Returns the [`AssetReferencesFns`] that use the given
+    /// [`HandleReferences`] type.
+    pub(crate) fn new<T, R>() -> Self
+    where
+        T: Any + Asset,
+        R: HandleReferences<T> + FromConfig + Send + 'static,
+    {
+        Self {
+            visit: asset_references::<T, R>,
+            visit_all: all_asset_references::<T, R>,
+        }
+    }
+}
+
+fn asset_references<T, R>(
+    world: &World,
+    handle_id: HandleId,
+    memory_config: &MemoryConfig,
+    config_generation: u64,
+    state: &mut EstimatorState,
+) -> Option<Vec<(TypeId, HandleId)>>
+where
+    T: Any + Asset,
+    R: HandleReferences<T> + FromConfig + Send + 'static,
+{
+    let asset = world.get_resource::<Assets<T>>()?.get(handle_id)?;
+    let references = persistent_estimator::<R>(state, memory_config, config_generation);
+
+    Some(visit_handles(asset, references))
+}
+
+fn all_asset_references<T, R>(
+    world: &World,
+    memory_config: &MemoryConfig,
+    config_generation: u64,
+    state: &mut EstimatorState,
+) -> Vec<Vec<(TypeId, HandleId)>>
+where
+    T: Any + Asset,
+    R: HandleReferences<T> + FromConfig + Send + 'static,
+{
+    let assets = match world.get_resource::<Assets<T>>() {
+        Some(assets) => assets,
+        None => return Vec::new(),
+    };
+
+    let references = persistent_estimator::<R>(state, memory_config, config_generation);
+
+    assets
+        .iter()
+        .map(|(_, asset)| visit_handles(asset, references))
+        .collect()
+}
+
+fn visit_handles<T, R>(value: &T, references: &R) -> Vec<(TypeId, HandleId)>
+where
+    R: HandleReferences<T>,
+{
+    let mut visitor = HandleVisitor::default();
+    references.visit_handles(value, &mut visitor);
+
+    visitor.handles
+}
+
+/// Sums fractional shares of [`MemoryStats`], for attributing parts of shared
+/// assets to their referrers.
+#[derive(Debug, Default, Clone, Copy)]
+pub(crate) struct SharedStats {
+    count: f64,
+    total_stack_bytes: f64,
+    total_heap_bytes: f64,
+    total_device_bytes: f64,
+}
+
+impl SharedStats {
+    /// Adds the given share of `stats`, where `1.0` is all of it.
+    pub(crate) fn add_share(&mut self, stats: MemoryStats, share: f64) {
+        self.count += stats.count as f64 * share;
+        self.total_stack_bytes += stats.total_stack_bytes as f64 * share;
+        self.total_heap_bytes += stats.total_heap_bytes as f64 * share;
+        self.total_device_bytes += stats.total_device_bytes as f64 * share;
+    }
+
+    /// Returns the summed shares, rounded to whole instances and bytes.
+    pub(crate) fn rounded(&self) -> MemoryStats {
+        MemoryStats {
+            count: self.count.round() as usize,
+            total_stack_bytes: self.total_stack_bytes.round() as usize,
+            total_heap_bytes: self.total_heap_bytes.round() as usize,
+            total_device_bytes: self.total_device_bytes.round() as usize,
+        }
+    }
+}
+
+/***************************************************************************************************
+
+                             dMMMMMMP dMMMMMP .dMMMb dMMMMMMP .dMMMb
+                               dMP   dMP     dMP" VP   dMP   dMP" VP
+                              dMP   dMMMP    VMMMb    dMP    VMMMb
+                             dMP   dMP     dP .dMP   dMP   dP .dMP
+                            dMP   dMMMMMP  VMMMP"   dMP    VMMMP"
+
+***************************************************************************************************/
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::{
+        mem::size_of,
+        sync::atomic::{AtomicUsize, Ordering},
+    };
+
+    use bevy::{
+        app::App,
+        asset::{AddAsset, AssetPlugin},
+        core::CorePlugin,
+        reflect::TypeUuid,
+    };
+
+    use crate::{DataSizeEstimator, MemoryUsage, TypeKind};
+
+    #[derive(TypeUuid)]
+    #[uuid = "0d3e5b5a-7c1f-4a43-9b7e-5f6f3a2f9c01"]
+    struct Texture(Vec<u8>);
+
+    #[derive(TypeUuid)]
+    #[uuid = "0d3e5b5a-7c1f-4a43-9b7e-5f6f3a2f9c02"]
+    struct Material {
+        texture: Handle<Texture>,
+    }
+
+    /// Holds the texture both directly and through the material.
+    #[derive(Component)]
+    struct Model {
+        material: Handle<Material>,
+        texture: Handle<Texture>,
+    }
+
+    #[derive(Default)]
+    struct TextureEstimator;
+
+    impl DataSizeEstimator<Texture> for TextureEstimator {
+        const IS_DYNAMIC: bool = true;
+
+        fn estimate_heap_size(&self, value: &Texture) -> usize {
+            value.0.len()
+        }
+    }
+
+    #[derive(Default)]
+    struct MaterialEstimator;
+
+    impl DataSizeEstimator<Material> for MaterialEstimator {
+        const IS_DYNAMIC: bool = false;
+
+        fn estimate_heap_size(&self, _value: &Material) -> usize {
+            0
+        }
+    }
+
+    #[derive(Default)]
+    struct MaterialReferences;
+
+    impl HandleReferences<Material> for MaterialReferences {
+        fn visit_handles(&self, material: &Material, visitor: &mut HandleVisitor) {
+            visitor.visit(&material.texture);
+        }
+    }
+
+    #[derive(Default)]
+    struct ModelReferences;
+
+    impl HandleReferences<Model> for ModelReferences {
+        fn visit_handles(&self, model: &Model, visitor: &mut HandleVisitor) {
+            visitor.visit(&model.material);
+            visitor.visit(&model.texture);
+        }
+    }
+
+    struct Scene {
+        app: App,
+        memory_usage: MemoryUsage,
+        material: Handle<Material>,
+        model: Entity,
+        sprite: Entity,
+    }
+
+    /// Sets up a texture that is referenced by a material, by a model that
+    /// also uses the material, and by a sprite.
+    fn scene() -> Scene {
+        let mut app = App::new();
+        app.add_plugin(CorePlugin)
+            .add_plugin(AssetPlugin)
+            .add_asset::<Texture>()
+            .add_asset::<Material>();
+
+        let texture = app
+            .world
+            .get_resource_mut::<Assets<Texture>>()
+            .unwrap()
+            .add(Texture(vec![0; 900]));
+        let material = app
+            .world
+            .get_resource_mut::<Assets<Material>>()
+            .unwrap()
+            .add(Material {
+                texture: texture.clone(),
+            });
+
+        let model = app
+            .world
+            .spawn()
+            .insert(Model {
+                material: material.clone(),
+                texture: texture.clone(),
+            })
+            .id();
+        let sprite = app.world.spawn().insert(texture).id();
+
+        let mut memory_usage = MemoryUsage::default();
+        memory_usage.register_type_with_kind::<Texture>(TypeKind::Asset);
+        memory_usage.register_asset_estimator::<Texture, TextureEstimator>();
+        memory_usage.register_type_with_kind::<Material>(TypeKind::Asset);
+        memory_usage.register_asset_estimator::<Material, MaterialEstimator>();
+        memory_usage.register_asset_references::<Material, MaterialReferences>();
+        memory_usage.register_type_with_kind::<Model>(TypeKind::Component);
+        memory_usage.register_component_references::<Model, ModelReferences>();
+
+        Scene {
+            app,
+            memory_usage,
+            material,
+            model,
+            sprite,
+        }
+    }
+
+    fn stats(count: usize, total_stack_bytes: usize, total_heap_bytes: usize) -> MemoryStats {
+        MemoryStats {
+            count,
+            total_stack_bytes,
+            total_heap_bytes,
+            total_device_bytes: 0,
+        }
+    }
+
+    #[test]
+    fn full_attribution_counts_each_asset_once() {
+        let scene = scene();
+        let world = &scene.app.world;
+
+        let deep = scene
+            .memory_usage
+            .measure_entity_deep(world, scene.model, HandleAttribution::Full)
+            .unwrap();
+
+        assert_eq!(deep.shallow(), MemoryStats::default());
+        assert_eq!(
+            deep.get::<Texture>(),
+            Some(stats(1, size_of::<Texture>(), 900))
+        );
+        assert_eq!(
+            deep.get::<Material>(),
+            Some(stats(1, size_of::<Material>(), 0))
+        );
+        assert_eq!(
+            deep.total(),
+            deep.get::<Texture>().unwrap() + deep.get::<Material>().unwrap()
+        );
+
+        let deep = scene
+            .memory_usage
+            .measure_entity_deep(world, scene.sprite, HandleAttribution::Full)
+            .unwrap();
+        assert_eq!(
+            deep.get::<Texture>(),
+            Some(stats(1, size_of::<Texture>(), 900))
+        );
+        assert_eq!(deep.get::<Material>(), None);
+    }
+
+    #[test]
+    fn proportional_attribution_follows_every_path() {
+        let scene = scene();
+        let world = &scene.app.world;
+
+        // The texture has three referrers: the model, the sprite and the
+        // material. The model holds its own third, and all of the material,
+        // which holds another third.
+        let model = scene
+            .memory_usage
+            .measure_entity_deep(world, scene.model, HandleAttribution::Proportional)
+            .unwrap();
+        let model_texture = model.get::<Texture>().unwrap();
+        assert_eq!(model_texture.total_heap_bytes, 600);
+        assert_eq!(model_texture.count, 1);
+        assert_eq!(
+            model.get::<Material>(),
+            Some(stats(1, size_of::<Material>(), 0))
+        );
+
+        let sprite = scene
+            .memory_usage
+            .measure_entity_deep(world, scene.sprite, HandleAttribution::Proportional)
+            .unwrap();
+        let sprite_texture = sprite.get::<Texture>().unwrap();
+        assert_eq!(sprite_texture.total_heap_bytes, 300);
+        assert_eq!(sprite_texture.count, 0);
+
+        // The shares of the texture add up to all of it.
+        assert_eq!(
+            model_texture + sprite_texture,
+            stats(1, size_of::<Texture>(), 900)
+        );
+    }
+
+    #[test]
+    fn proportional_attribution_of_asset_excludes_itself() {
+        let scene = scene();
+        let world = &scene.app.world;
+
+        let deep = scene
+            .memory_usage
+            .measure_asset_deep::<Material>(world, &scene.material, HandleAttribution::Proportional)
+            .unwrap();
+
+        assert_eq!(deep.shallow(), stats(1, size_of::<Material>(), 0));
+        assert_eq!(deep.get::<Material>(), None);
+        assert_eq!(deep.get::<Texture>().unwrap().total_heap_bytes, 300);
+    }
+
+    static CREATED: AtomicUsize = AtomicUsize::new(0);
+
+    struct CountingModelReferences;
+
+    impl FromConfig for CountingModelReferences {
+        fn from_config(_config: &MemoryConfig) -> Self {
+            CREATED.fetch_add(1, Ordering::SeqCst);
+
+            Self
+        }
+    }
+
+    impl HandleReferences<Model> for CountingModelReferences {
+        fn visit_handles(&self, model: &Model, visitor: &mut HandleVisitor) {
+            ModelReferences.visit_handles(model, visitor);
+        }
+    }
+
+    #[test]
+    fn reuses_handle_references_until_config_changes() {
+        let mut scene = scene();
+        scene
+            .memory_usage
+            .register_component_references::<Model, CountingModelReferences>();
+        let world = &scene.app.world;
+
+        // Counting the referrers visits every entity, but the references are
+        // only created once.
+        for _ in 0..2 {
+            scene
+                .memory_usage
+                .measure_entity_deep(world, scene.model, HandleAttribution::Proportional)
+                .unwrap();
+        }
+        assert_eq!(CREATED.load(Ordering::SeqCst), 1);
+
+        scene.memory_usage.config_changed();
+
+        scene
+            .memory_usage
+            .measure_entity_deep(world, scene.model, HandleAttribution::Proportional)
+            .unwrap();
+        assert_eq!(CREATED.load(Ordering::SeqCst), 2);
+    }
+}
@@ -0,0 +1,429 @@
+use std::fmt;
+
+use bytesize::ByteSize;
+
+use crate::{MemoryReport, MemoryStats, TypeInfo};
+
+/// The difference between two [`MemoryReport`]s.
+///
+/// Use [`MemoryReport::diff`] to create one.
+#[derive(Debug, Clone)]
+pub struct MemoryReportDiff {
+    from_frame: u64,
+    to_frame: u64,
+    types: Vec<TypeDiff>,
+}
+
+impl MemoryReportDiff {
+    /// Returns the frame of the earlier report.
+    #[inline]
+    pub fn from_frame(&self) -> u64 {
+        self.from_frame
+    }
+
+    /// Returns the frame of the later report.
+    #[inline]
+    pub fn to_frame(&self) -> u64 {
+        self.to_frame
+    }
+
+    /// Returns the difference for every type that appears in either report.
+    ///
+    /// The types are ordered by the absolute value of the change in their
+    /// total number of bytes, including device bytes, largest first.
+    #[inline]
+    pub fn types(&self) -> &[TypeDiff] {
+        &self.types
+    }
+
+    /// Returns the total change across all types.
+    pub fn total(&self) -> MemoryStatsDelta {
+        self.types
+            .iter()
+            .fold(MemoryStatsDelta::default(), |total, diff| {
+                total + diff.delta
+            })
+    }
+}
+
+impl fmt::Display for MemoryReportDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "Memory usage changes from frame {} to frame {}:",
+            self.from_frame, self.to_frame
+        )?;
+
+        for diff in self.types.iter() {
+            let note = match diff.change {
+                TypeChange::Appeared => " (appeared)",
+                TypeChange::Disappeared => " (disappeared)",
+                TypeChange::Present => "",
+            };
+
+            writeln!(f, "{}: {}{note}", diff.info.short_name, diff.delta)?;
+        }
+
+        write!(f, "Total: {}", self.total())
+    }
+}
+
+/// The difference in the [`MemoryStats`] of a single type between two
+/// [`MemoryReport`]s.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TypeDiff {
+    /// The type that changed.
+    pub info: TypeInfo,
+
+    /// Whether instances of the type appeared or disappeared.
+    pub change: TypeChange,
+
+    /// The stats of the type in the earlier report, if it was present.
+    pub before: Option<MemoryStats>,
+
+    /// The stats of the type in the later report, if it was present.
+    pub after: Option<MemoryStats>,
+
+    /// The change from `before` to `after`.
+    ///
+    /// A type that is missing from a report counts as having zero stats.
+    pub delta: MemoryStatsDelta,
+}
+
+/// Indicates whether instances of a type appeared or disappeared between the
+/// [`MemoryReport`]s that were compared.
+///
+/// Types stay registered once they are, so a type counts as missing from a
+/// report if it is not in it or if it has no instances in it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TypeChange {
+    /// The type had instances in both reports, or in neither.
+    Present,
+
+    /// The type only had instances in the later report.
+    Appeared,
+
+    /// The type only had instances in the earlier report.
+    Disappeared,
+}
+
+impl TypeChange {
+    fn between(before: Option<MemoryStats>, after: Option<MemoryStats>) -> Self {
+        let had_instances = |stats: Option<MemoryStats>| stats.map_or(0, |stats| stats.count) > 0;
+
+        match (had_instances(before), had_instances(after)) {
+            (false, true) => Self::Appeared,
+            (true, false) => Self::Disappeared,
+            _ => Self::Present,
+        }
+    }
+}
+
+/// The signed difference between two [`MemoryStats`].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct MemoryStatsDelta {
+    /// The change in the number of instances.
+    pub count: isize,
+
+    /// The change in the number of "stack" bytes.
+    pub total_stack_bytes: isize,
+
+    /// The change in the estimated number of heap bytes.
+    pub total_heap_bytes: isize,
+
+    /// The change in the estimated number of device bytes.
+    pub total_device_bytes: isize,
+}
+
+impl MemoryStatsDelta {
+    /// Returns the change from `before` to `after`.
+    pub fn between(before: MemoryStats, after: MemoryStats) -> Self {
+        Self {
+            count: after.count as isize - before.count as isize,
+            total_stack_bytes: after.total_stack_bytes as isize - before.total_stack_bytes as isize,
+            total_heap_bytes: after.total_heap_bytes as isize - before.total_heap_bytes as isize,
+            total_device_bytes: after.total_device_bytes as isize
+                - before.total_device_bytes as isize,
+        }
+    }
+
+    /// Returns the sum of `total_stack_bytes` and `total_heap_bytes` for
+    /// `self`.
+    #[inline]
+    pub fn total_bytes(&self) -> isize {
+        self.total_stack_bytes + self.total_heap_bytes
+    }
+
+    /// Returns the sum of `total_bytes()` and `total_device_bytes` for
+    /// `self`.
+    #[inline]
+    pub fn total_including_device(&self) -> isize {
+        self.total_bytes() + self.total_device_bytes
+    }
+
+    /// Returns `true` if none of the fields changed.
+    #[inline]
+    pub fn is_zero(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+impl std::ops::Add for MemoryStatsDelta {
+    type Output = MemoryStatsDelta;
+
+    #[inline]
+    fn add(self, rhs: Self) -> Self::Output {
+        Self {
+            count: self.count + rhs.count,
+            total_stack_bytes: self.total_stack_bytes + rhs.total_stack_bytes,
+            total_heap_bytes: self.total_heap_bytes + rhs.total_heap_bytes,
+            total_device_bytes: self.total_device_bytes + rhs.total_device_bytes,
+        }
+    }
+}
+
+impl fmt::Display for MemoryStatsDelta {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let count = self.count;
+        let sign = if self.total_bytes() < 0 { '-' } else { '+' };
+        let total_bytes = ByteSize(self.total_bytes().unsigned_abs() as u64);
+
+        if self.total_device_bytes == 0 {
+            write!(f, "{count:+} ({sign}{total_bytes})")
+        } else {
+            let device_sign = if self.total_device_bytes < 0 {
+                '-'
+            } else {
+                '+'
+            };
+            let total_device_bytes = ByteSize(self.total_device_bytes.unsigned_abs() as u64);
+
+            write!(
+                f,
+                "{count:+} ({sign}{total_bytes}, {device_sign}{total_device_bytes} device)"
+            )
+        }
+    }
+}
+
+pub(crate) fn diff_reports(before: &MemoryReport, after: &MemoryReport) -> MemoryReportDiff {
+    let mut types: Vec<TypeDiff> = after
+        .types()
+        .iter()
+        .map(|(info, after_stats)| {
+            let before_stats = before.get_by_type_id(info.type_id);
+
+            TypeDiff {
+                info: info.clone(),
+                change: TypeChange::between(before_stats, Some(*after_stats)),
+                before: before_stats,
+                after: Some(*after_stats),
+                delta: MemoryStatsDelta::between(before_stats.unwrap_or_default(), *after_stats),
+            }
+        })
+        .collect();
+
+    types.extend(
+        before
+            .types()
+            .iter()
+            .filter(|(info, _)| after.get_by_type_id(info.type_id).is_none())
+            .map(|(info, before_stats)| TypeDiff {
+                info: info.clone(),
+                change: TypeChange::between(Some(*before_stats), None),
+                before: Some(*before_stats),
+                after: None,
+                delta: MemoryStatsDelta::between(*before_stats, MemoryStats::default()),
+            }),
+    );
+
+    // Sort by the size of the change, largest first. Ties are broken by the
+    // order in which the types appear in the reports.
+    types.sort_by_key(|diff| std::cmp::Reverse(diff.delta.total_including_device().unsigned_abs()));
+
+    MemoryReportDiff {
+        from_frame: before.frame(),
+        to_frame: after.frame(),
+        types,
+    }
+}
+
+/***************************************************************************************************
+
+                             dMMMMMMP dMMMMMP .dMMMb dMMMMMMP .dMMMb
+                               dMP   dMP     dMP" VP   dMP   dMP" VP
+                              dMP   dMMMP    VMMMb    dMP    VMMMb
+                             dMP   dMP     dP .dMP   dMP   dP .dMP
+                            dMP   dMMMMMP  VMMMP"   dMP    VMMMP"
+
+***************************************************************************************************/
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::time::SystemTime;
+
+    use crate::TypeKind;
+
+    struct A;
+    struct B;
+    struct C;
+
+    fn stats(count: usize, total_heap_bytes: usize) -> MemoryStats {
+        MemoryStats {
+            count,
+            total_stack_bytes: 0,
+            total_heap_bytes,
+            total_device_bytes: 0,
+        }
+    }
+
+    fn report(frame: u64, types: Vec<(TypeInfo, MemoryStats)>) -> MemoryReport {
+        MemoryReport::new(frame, SystemTime::now(), types)
+    }
+
+    #[test]
+    fn computes_signed_deltas_sorted_by_absolute_growth() {
+        let before = report(
+            1,
+            vec![
+                (TypeInfo::of::<A>(TypeKind::Custom), stats(10, 1000)),
+                (TypeInfo::of::<B>(TypeKind::Custom), stats(10, 1000)),
+            ],
+        );
+        let after = report(
+            2,
+            vec![
+                (TypeInfo::of::<A>(TypeKind::Custom), stats(12, 1100)),
+                (TypeInfo::of::<B>(TypeKind::Custom), stats(5, 500)),
+            ],
+        );
+
+        let diff = before.diff(&after);
+
+        assert_eq!(diff.from_frame(), 1);
+        assert_eq!(diff.to_frame(), 2);
+
+        let deltas: Vec<_> = diff
+            .types()
+            .iter()
+            .map(|diff| {
+                (
+                    diff.info.short_name.as_str(),
+                    diff.delta.count,
+                    diff.delta.total_bytes(),
+                )
+            })
+            .collect();
+        assert_eq!(deltas, vec![("B", -5, -500), ("A", 2, 100)]);
+        assert_eq!(diff.total().total_bytes(), -400);
+    }
+
+    #[test]
+    fn flags_types_that_appeared_or_disappeared() {
+        let before = report(
+            1,
+            vec![
+                (TypeInfo::of::<A>(TypeKind::Custom), stats(1, 10)),
+                (TypeInfo::of::<B>(TypeKind::Custom), stats(1, 20)),
+            ],
+        );
+        let after = report(
+            2,
+            vec![
+                (TypeInfo::of::<A>(TypeKind::Custom), stats(1, 10)),
+                (TypeInfo::of::<C>(TypeKind::Custom), stats(1, 30)),
+            ],
+        );
+
+        let diff = before.diff(&after);
+
+        let changes: Vec<_> = diff
+            .types()
+            .iter()
+            .map(|diff| (diff.info.short_name.as_str(), diff.change))
+            .collect();
+        assert_eq!(
+            changes,
+            vec![
+                ("C", TypeChange::Appeared),
+                ("B", TypeChange::Disappeared),
+                ("A", TypeChange::Present),
+            ]
+        );
+        assert!(diff.types()[2].delta.is_zero());
+    }
+
+    #[test]
+    fn flags_types_whose_instances_appeared_or_disappeared() {
+        // Types never unregister, so loading and unloading a level only
+        // changes their counts.
+        let before = report(
+            1,
+            vec![
+                (TypeInfo::of::<A>(TypeKind::Custom), stats(0, 0)),
+                (TypeInfo::of::<B>(TypeKind::Custom), stats(3, 20)),
+                (TypeInfo::of::<C>(TypeKind::Custom), stats(0, 0)),
+            ],
+        );
+        let after = report(
+            2,
+            vec![
+                (TypeInfo::of::<A>(TypeKind::Custom), stats(2, 30)),
+                (TypeInfo::of::<B>(TypeKind::Custom), stats(0, 0)),
+                (TypeInfo::of::<C>(TypeKind::Custom), stats(0, 0)),
+            ],
+        );
+
+        let diff = before.diff(&after);
+
+        let changes: Vec<_> = diff
+            .types()
+            .iter()
+            .map(|diff| (diff.info.short_name.as_str(), diff.change))
+            .collect();
+        assert_eq!(
+            changes,
+            vec![
+                ("A", TypeChange::Appeared),
+                ("B", TypeChange::Disappeared),
+                ("C", TypeChange::Present),
+            ]
+        );
+    }
+
+    #[test]
+    fn sorts_by_change_including_device_bytes() {
+        let texture = |total_device_bytes| MemoryStats {
+            total_device_bytes,
+            ..stats(1, 10)
+        };
+
+        let before = report(
+            1,
+            vec![
+                (TypeInfo::of::<A>(TypeKind::Custom), stats(1, 1000)),
+                (TypeInfo::of::<B>(TypeKind::Custom), texture(1000)),
+            ],
+        );
+        let after = report(
+            2,
+            vec![
+                (TypeInfo::of::<A>(TypeKind::Custom), stats(1, 1100)),
+                (TypeInfo::of::<B>(TypeKind::Custom), texture(5000)),
+            ],
+        );
+
+        let diff = before.diff(&after);
+
+        let names: Vec<_> = diff
+            .types()
+            .iter()
+            .map(|diff| diff.info.short_name.as_str())
+            .collect();
+        assert_eq!(names, vec!["B", "A"]);
+        assert_eq!(diff.types()[0].delta.total_bytes(), 0);
+        assert_eq!(diff.types()[0].delta.total_including_device(), 4000);
+    }
+}
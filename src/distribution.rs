@@ -0,0 +1,165 @@
+use crate::MemoryStats;
+
+/// Describes how the total sizes of the individual instances of a type are
+/// distributed.
+///
+/// This is only computed if [`MemoryConfig::size_distributions`] is enabled.
+///
+/// [`MemoryConfig::size_distributions`]: crate::MemoryConfig::size_distributions
+#[derive(Debug, Default, Clone, PartialEq, Eq, Hash)]
+pub struct SizeDistribution {
+    /// The number of instances.
+    pub count: usize,
+
+    /// The total size of the smallest instance, in bytes.
+    pub min: usize,
+
+    /// The total size of the largest instance, in bytes.
+    pub max: usize,
+
+    /// The mean total size of all instances, rounded down, in bytes.
+    pub mean: usize,
+
+    /// The median total size of all instances, in bytes.
+    pub median: usize,
+
+    /// The 95th percentile of the total sizes of all instances, in bytes.
+    pub p95: usize,
+
+    /// The number of instances in each power-of-two size class.
+    ///
+    /// The entry at index `i` counts the instances whose total size is at
+    /// least `2^i` bytes and less than `2^(i + 1)` bytes. Instances of size
+    /// `0` are counted in the first entry.
+    pub histogram: Vec<usize>,
+}
+
+impl SizeDistribution {
+    /// Computes the distribution of the total sizes of the given instances,
+    /// including their device memory.
+    ///
+    /// Each item is expected to hold the [`MemoryStats`] of a single instance.
+    pub fn from_instances<I>(instances: I) -> Self
+    where
+        I: IntoIterator<Item = MemoryStats>,
+    {
+        Self::from_sizes(
+            instances
+                .into_iter()
+                .map(|stats| stats.total_including_device()),
+        )
+    }
+
+    /// Computes the distribution of the given sizes.
+    pub fn from_sizes<I>(sizes: I) -> Self
+    where
+        I: IntoIterator<Item = usize>,
+    {
+        let mut sizes: Vec<usize> = sizes.into_iter().collect();
+        if sizes.is_empty() {
+            return Self::default();
+        }
+
+        sizes.sort_unstable();
+
+        let count = sizes.len();
+        let sum: u128 = sizes.iter().map(|&size| size as u128).sum();
+
+        let mut histogram = Vec::new();
+        for &size in sizes.iter() {
+            let class = size_class(size);
+            if histogram.len() <= class {
+                histogram.resize(class + 1, 0);
+            }
+            histogram[class] += 1;
+        }
+
+        Self {
+            count,
+            min: sizes[0],
+            max: sizes[count - 1],
+            mean: (sum / count as u128) as usize,
+            median: percentile(&sizes, 50),
+            p95: percentile(&sizes, 95),
+            histogram,
+        }
+    }
+}
+
+/// Returns the index of the power-of-two size class of the given size.
+#[inline]
+fn size_class(size: usize) -> usize {
+    if size == 0 {
+        0
+    } else {
+        (usize::BITS - 1 - size.leading_zeros()) as usize
+    }
+}
+
+/// Returns the given percentile of the sorted `values` using the nearest-rank
+/// method.
+fn percentile(sorted_values: &[usize], percent: u32) -> usize {
+    let rank = (sorted_values.len() as f64 * f64::from(percent) / 100.0).ceil() as usize;
+
+    sorted_values[rank.max(1) - 1]
+}
+
+/***************************************************************************************************
+
+                             dMMMMMMP dMMMMMP .dMMMb dMMMMMMP .dMMMb
+                               dMP   dMP     dMP" VP   dMP   dMP" VP
+                              dMP   dMMMP    VMMMb    dMP    VMMMb
+                             dMP   dMP     dP .dMP   dMP   dP .dMP
+                            dMP   dMMMMMP  VMMMP"   dMP    VMMMP"
+
+***************************************************************************************************/
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn empty() {
+        assert_eq!(
+            SizeDistribution::from_sizes([]),
+            SizeDistribution::default()
+        );
+    }
+
+    #[test]
+    fn computes_summary_statistics() {
+        let distribution = SizeDistribution::from_sizes((1..=100).rev());
+
+        assert_eq!(distribution.count, 100);
+        assert_eq!(distribution.min, 1);
+        assert_eq!(distribution.max, 100);
+        assert_eq!(distribution.mean, 50);
+        assert_eq!(distribution.median, 50);
+        assert_eq!(distribution.p95, 95);
+    }
+
+    #[test]
+    fn tells_apart_one_large_instance() {
+        let mut sizes = vec![10; 99];
+        sizes.push(300_000_000);
+
+        let distribution = SizeDistribution::from_sizes(sizes);
+
+        assert_eq!(distribution.median, 10);
+        assert_eq!(distribution.p95, 10);
+        assert_eq!(distribution.max, 300_000_000);
+    }
+
+    #[test]
+    fn histogram_uses_power_of_two_size_classes() {
+        let distribution = SizeDistribution::from_sizes([0, 1, 2, 3, 4, 7, 8, 1024]);
+
+        let mut expected = vec![0; 11];
+        expected[0] = 2;
+        expected[1] = 2;
+        expected[2] = 2;
+        expected[3] = 1;
+        expected[10] = 1;
+        assert_eq!(distribution.histogram, expected);
+    }
+}
@@ -0,0 +1,334 @@
+use std::{
+    any::{Any, TypeId},
+    fmt,
+};
+
+use bevy::{
+    asset::{Asset, Assets, Handle, HandleId},
+    ecs::{component::Component, entity::Entity, world::World},
+};
+
+use crate::{
+    estimator::FromConfig, systems::PersistentEstimator, DataSizeEstimator, MemoryConfig,
+    MemoryStats, TypeInfo,
+};
+
+/// The estimated memory usage of the registered components of a single entity.
+///
+/// Use [`MemoryUsage::measure_entity`] to create one.
+///
+/// [`MemoryUsage::measure_entity`]: crate::MemoryUsage::measure_entity
+#[derive(Debug, Clone)]
+pub struct EntityMemory {
+    entity: Entity,
+    components: Vec<(TypeInfo, MemoryStats)>,
+}
+
+impl EntityMemory {
+    pub(crate) fn new(entity: Entity, mut components: Vec<(TypeInfo, MemoryStats)>) -> Self {
+        components.sort_by(|(a_info, a), (b_info, b)| {
+            b.total_including_device()
+                .cmp(&a.total_including_device())
+                .then_with(|| a_info.short_name.cmp(&b_info.short_name))
+        });
+
+        Self { entity, components }
+    }
+
+    /// Returns the entity that was measured.
+    #[inline]
+    pub fn entity(&self) -> Entity {
+        self.entity
+    }
+
+    /// Returns the [`TypeInfo`] and [`MemoryStats`] of every registered
+    /// component on the entity, largest first.
+    #[inline]
+    pub fn components(&self) -> &[(TypeInfo, MemoryStats)] {
+        &self.components
+    }
+
+    /// Returns the [`MemoryStats`] of the given component on the entity.
+    ///
+    /// Returns `None` if the entity does not have the component, or if the
+    /// component type is not registered.
+    pub fn get<T>(&self) -> Option<MemoryStats>
+    where
+        T: Any,
+    {
+        let type_id = TypeId::of::<T>();
+
+        self.components
+            .iter()
+            .find(|(info, _)| info.type_id == type_id)
+            .map(|(_, stats)| *stats)
+    }
+
+    /// Returns the sum of the [`MemoryStats`] of all registered components on
+    /// the entity.
+    pub fn total(&self) -> MemoryStats {
+        self.components.iter().map(|(_, stats)| *stats).sum()
+    }
+}
+
+impl fmt::Display for EntityMemory {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Memory usage of entity {:?}:", self.entity)?;
+
+        for (info, stats) in self.components.iter() {
+            writeln!(f, "{}: {stats}", info.short_name)?;
+        }
+
+        write!(f, "Total: {}", self.total())
+    }
+}
+
+/// Estimates the [`MemoryStats`] of a single component on an entity.
+///
+/// The estimator is kept in the given [`EstimatorState`] between calls, and is
+/// only recreated when the given config generation changes.
+pub(crate) type ComponentEstimatorFn =
+    fn(&World, Entity, &MemoryConfig, u64, &mut EstimatorState) -> Option<MemoryStats>;
+
+/// Type-erased storage for the [`PersistentEstimator`] of a single type.
+pub(crate) type EstimatorState = Option<Box<dyn Any + Send>>;
+
+/// Returns the estimator of type `E` that is kept in `state`, creating or
+/// recreating it first if necessary.
+///
+/// This also works for any other [`FromConfig`] type, such as
+/// [`HandleReferences`][crate::HandleReferences].
+pub(crate) fn persistent_estimator<'a, E>(
+    state: &'a mut EstimatorState,
+    memory_config: &MemoryConfig,
+    config_generation: u64,
+) -> &'a E
+where
+    E: FromConfig + Send + 'static,
+{
+    let estimator = state
+        .get_or_insert_with(|| Box::new(PersistentEstimator::<E>::default()))
+        .downcast_mut::<PersistentEstimator<E>>()
+        .expect("Estimator state holds a different estimator type");
+
+    estimator.update_generation(config_generation);
+    estimator.get(memory_config)
+}
+
+/// A [`ComponentEstimatorFn`] that uses the given [`DataSizeEstimator`] type.
+pub(crate) fn estimate_component<T, E>(
+    world: &World,
+    entity: Entity,
+    memory_config: &MemoryConfig,
+    config_generation: u64,
+    state: &mut EstimatorState,
+) -> Option<MemoryStats>
+where
+    T: Any + Component,
+    E: DataSizeEstimator<T> + FromConfig + Send + 'static,
+{
+    let component = world.get::<T>(entity)?;
+    let estimator = persistent_estimator::<E>(state, memory_config, config_generation);
+
+    Some(MemoryStats::from_value_with_estimator(component, estimator))
+}
+
+/// Type-erased functions for finding and estimating the assets of a single
+/// type that are referenced by entities.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct AssetEstimatorFns {
+    /// Returns the [`HandleId`] of the entity's [`Handle`] component.
+    pub(crate) handle_of: fn(&World, Entity) -> Option<HandleId>,
+
+    /// Estimates the [`MemoryStats`] of the asset with the given [`HandleId`],
+    /// keeping the estimator in the given [`EstimatorState`] like a
+    /// [`ComponentEstimatorFn`].
+    pub(crate) estimate:
+        fn(&World, HandleId, &MemoryConfig, u64, &mut EstimatorState) -> Option<MemoryStats>,
+}
+
+impl AssetEstimatorFns {
+    /// Returns the [`AssetEstimatorFns`] that use the given
+    /// [`DataSizeEstimator`] type.
+    pub(crate) fn new<T, E>() -> Self
+    where
+        T: Any + Asset,
+        E: DataSizeEstimator<T> + FromConfig + Send + 'static,
+    {
+        Self {
+            handle_of: handle_of::<T>,
+            estimate: estimate_asset::<T, E>,
+        }
+    }
+}
+
+fn handle_of<T>(world: &World, entity: Entity) -> Option<HandleId>
+where
+    T: Asset,
+{
+    world.get::<Handle<T>>(entity).map(|handle| handle.id)
+}
+
+fn estimate_asset<T, E>(
+    world: &World,
+    handle_id: HandleId,
+    memory_config: &MemoryConfig,
+    config_generation: u64,
+    state: &mut EstimatorState,
+) -> Option<MemoryStats>
+where
+    T: Any + Asset,
+    E: DataSizeEstimator<T> + FromConfig + Send + 'static,
+{
+    let asset = world.get_resource::<Assets<T>>()?.get(handle_id)?;
+    let estimator = persistent_estimator::<E>(state, memory_config, config_generation);
+
+    Some(MemoryStats::from_value_with_estimator(asset, estimator))
+}
+
+/***************************************************************************************************
+
+                             dMMMMMMP dMMMMMP .dMMMb dMMMMMMP .dMMMb
+                               dMP   dMP     dMP" VP   dMP   dMP" VP
+                              dMP   dMMMP    VMMMb    dMP    VMMMb
+                             dMP   dMP     dP .dMP   dMP   dP .dMP
+                            dMP   dMMMMMP  VMMMP"   dMP    VMMMP"
+
+***************************************************************************************************/
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use crate::{MemoryUsage, TypeKind};
+
+    #[derive(Component)]
+    struct Buffer(Vec<u8>);
+
+    #[derive(Component)]
+    struct Marker;
+
+    #[derive(Component)]
+    struct Unregistered;
+
+    #[derive(Default)]
+    struct BufferEstimator;
+
+    impl DataSizeEstimator<Buffer> for BufferEstimator {
+        const IS_DYNAMIC: bool = true;
+
+        fn estimate_heap_size(&self, value: &Buffer) -> usize {
+            value.0.len()
+        }
+    }
+
+    #[derive(Default)]
+    struct MarkerEstimator;
+
+    impl DataSizeEstimator<Marker> for MarkerEstimator {
+        const IS_DYNAMIC: bool = false;
+
+        fn estimate_heap_size(&self, _value: &Marker) -> usize {
+            0
+        }
+    }
+
+    static CREATED: AtomicUsize = AtomicUsize::new(0);
+
+    struct CountingEstimator;
+
+    impl FromConfig for CountingEstimator {
+        fn from_config(_config: &MemoryConfig) -> Self {
+            CREATED.fetch_add(1, Ordering::SeqCst);
+
+            Self
+        }
+    }
+
+    impl DataSizeEstimator<Unregistered> for CountingEstimator {
+        const IS_DYNAMIC: bool = false;
+
+        fn estimate_heap_size(&self, _value: &Unregistered) -> usize {
+            0
+        }
+    }
+
+    fn memory_usage() -> MemoryUsage {
+        let mut memory_usage = MemoryUsage::default();
+
+        memory_usage.register_type_with_kind::<Buffer>(TypeKind::Component);
+        memory_usage.register_component_estimator::<Buffer, BufferEstimator>();
+        memory_usage.register_type_with_kind::<Marker>(TypeKind::Component);
+        memory_usage.register_component_estimator::<Marker, MarkerEstimator>();
+
+        memory_usage
+    }
+
+    #[test]
+    fn measures_registered_components_largest_first() {
+        let memory_usage = memory_usage();
+
+        let mut world = World::new();
+        let entity = world
+            .spawn()
+            .insert(Marker)
+            .insert(Buffer(vec![0; 100]))
+            .insert(Unregistered)
+            .id();
+
+        let memory = memory_usage.measure_entity(&world, entity).unwrap();
+
+        assert_eq!(memory.entity(), entity);
+
+        let names: Vec<_> = memory
+            .components()
+            .iter()
+            .map(|(info, _)| info.short_name.as_str())
+            .collect();
+        assert_eq!(names, vec!["Buffer", "Marker"]);
+
+        let buffer = memory.get::<Buffer>().unwrap();
+        assert_eq!(buffer.count, 1);
+        assert_eq!(buffer.total_heap_bytes, 100);
+        assert!(memory.get::<Unregistered>().is_none());
+        assert_eq!(memory.total(), buffer + memory.get::<Marker>().unwrap());
+    }
+
+    #[test]
+    fn measures_missing_entities_as_none() {
+        let memory_usage = memory_usage();
+
+        let mut world = World::new();
+        let empty = world.spawn().id();
+        let despawned = world.spawn().insert(Marker).id();
+        world.despawn(despawned);
+
+        let memory = memory_usage.measure_entity(&world, empty).unwrap();
+        assert!(memory.components().is_empty());
+        assert_eq!(memory.total(), MemoryStats::default());
+
+        assert!(memory_usage.measure_entity(&world, despawned).is_none());
+    }
+
+    #[test]
+    fn reuses_estimator_until_config_changes() {
+        let mut memory_usage = MemoryUsage::default();
+        memory_usage.register_type_with_kind::<Unregistered>(TypeKind::Component);
+        memory_usage.register_component_estimator::<Unregistered, CountingEstimator>();
+
+        let mut world = World::new();
+        let entity = world.spawn().insert(Unregistered).id();
+
+        memory_usage.measure_entity(&world, entity).unwrap();
+        memory_usage.measure_entity(&world, entity).unwrap();
+        assert_eq!(CREATED.load(Ordering::SeqCst), 1);
+
+        memory_usage.config_changed();
+
+        memory_usage.measure_entity(&world, entity).unwrap();
+        memory_usage.measure_entity(&world, entity).unwrap();
+        assert_eq!(CREATED.load(Ordering::SeqCst), 2);
+    }
+}
@@ -1,6 +1,8 @@
 //! Heap size estimators.
 
-use crate::{DataSize, MemoryConfig};
+use std::sync::Arc;
+
+use crate::{DataSize, MemoryConfig, SharedAllocations, VisitSharedAllocations};
 
 /// Indicates that a type can estimate the heap usage of values of type `T`.
 ///
@@ -16,6 +18,31 @@ pub trait DataSizeEstimator<T: ?Sized> {
     /// Does not include data on the stack, which is usually determined using
     /// [`std::mem::size_of`].
     fn estimate_heap_size(&self, value: &T) -> usize;
+
+    /// Estimates the size of device (GPU) memory taken up by the given value,
+    /// such as the buffers and textures of render assets.
+    ///
+    /// This is only called if [`IS_DYNAMIC`][Self::IS_DYNAMIC] is `true`. By
+    /// default, values use no device memory.
+    #[inline]
+    fn estimate_device_size(&self, value: &T) -> usize {
+        let _ = value;
+
+        0
+    }
+
+    /// Reports the shared allocations, such as the payloads of [`Arc`]s, that
+    /// are included in the heap size of the given value.
+    ///
+    /// This allows [`MemoryStats::from_values_with_shared_allocations`] to
+    /// count each shared allocation only once. By default, no shared
+    /// allocations are reported.
+    ///
+    /// [`MemoryStats::from_values_with_shared_allocations`]: crate::MemoryStats::from_values_with_shared_allocations
+    #[inline]
+    fn visit_shared_allocations(&self, value: &T, shared: &mut SharedAllocations) {
+        let _ = (value, shared);
+    }
 }
 
 /// A [`DataSizeEstimator`] that simply forwards to a type's implementation of
@@ -32,6 +59,26 @@ impl<T: DataSize> DataSizeEstimator<T> for ForwardingEstimator {
     }
 }
 
+/// A [`DataSizeEstimator`] that forwards to a type's implementation of
+/// [`DataSize`], and reports its shared allocations through its
+/// implementation of [`VisitSharedAllocations`].
+#[derive(Default)]
+pub struct SharedForwardingEstimator;
+
+impl<T: DataSize + VisitSharedAllocations> DataSizeEstimator<T> for SharedForwardingEstimator {
+    const IS_DYNAMIC: bool = <T as DataSize>::IS_DYNAMIC;
+
+    #[inline]
+    fn estimate_heap_size(&self, value: &T) -> usize {
+        <T as DataSize>::estimate_heap_size(value)
+    }
+
+    #[inline]
+    fn visit_shared_allocations(&self, value: &T, shared: &mut SharedAllocations) {
+        value.visit_shared_allocations(shared);
+    }
+}
+
 /// A [`DataSizeEstimator`] that simply returns `0`.
 #[derive(Default)]
 pub struct ZeroEstimator;
@@ -59,6 +106,25 @@ impl<T> DataSizeEstimator<[T]> for SliceEstimator {
     }
 }
 
+/// A [`DataSizeEstimator`] for [`Arc`]s that reports their payload as a
+/// shared allocation.
+#[derive(Default)]
+pub struct ArcEstimator;
+
+impl<T: DataSize> DataSizeEstimator<Arc<T>> for ArcEstimator {
+    const IS_DYNAMIC: bool = true;
+
+    #[inline]
+    fn estimate_heap_size(&self, value: &Arc<T>) -> usize {
+        std::mem::size_of::<T>() + <T as DataSize>::estimate_heap_size(&**value)
+    }
+
+    #[inline]
+    fn visit_shared_allocations(&self, value: &Arc<T>, shared: &mut SharedAllocations) {
+        shared.visit_arc(value);
+    }
+}
+
 /// Creates `Self` using data from the given [`MemoryConfig`].
 pub trait FromConfig {
     /// Creates `Self` using data from the given [`MemoryConfig`].
@@ -0,0 +1,237 @@
+use std::{
+    any::{Any, TypeId},
+    fmt,
+};
+
+use bevy::ecs::entity::Entity;
+
+use crate::{MemoryStats, TypeInfo, TypeKind};
+
+/// The estimated memory usage of an entity and all of its descendants.
+///
+/// Use [`MemoryUsage::measure_subtree`] to create one.
+///
+/// [`MemoryUsage::measure_subtree`]: crate::MemoryUsage::measure_subtree
+#[derive(Debug, Clone)]
+pub struct SubtreeMemory {
+    root: Entity,
+    entity_count: usize,
+    types: Vec<(TypeInfo, MemoryStats)>,
+}
+
+impl SubtreeMemory {
+    pub(crate) fn new(
+        root: Entity,
+        entity_count: usize,
+        mut types: Vec<(TypeInfo, MemoryStats)>,
+    ) -> Self {
+        types.sort_by(|(a_info, a), (b_info, b)| {
+            b.total_including_device()
+                .cmp(&a.total_including_device())
+                .then_with(|| a_info.short_name.cmp(&b_info.short_name))
+        });
+
+        Self {
+            root,
+            entity_count,
+            types,
+        }
+    }
+
+    /// Returns the root entity of the subtree.
+    #[inline]
+    pub fn root(&self) -> Entity {
+        self.root
+    }
+
+    /// Returns the number of entities in the subtree, including the root.
+    #[inline]
+    pub fn entity_count(&self) -> usize {
+        self.entity_count
+    }
+
+    /// Returns the summed [`MemoryStats`] of every registered component type
+    /// found in the subtree, and of every registered asset type referenced by
+    /// it, largest first.
+    #[inline]
+    pub fn types(&self) -> &[(TypeInfo, MemoryStats)] {
+        &self.types
+    }
+
+    /// Returns the summed [`MemoryStats`] of the given type in the subtree.
+    pub fn get<T>(&self) -> Option<MemoryStats>
+    where
+        T: Any,
+    {
+        let type_id = TypeId::of::<T>();
+
+        self.types
+            .iter()
+            .find(|(info, _)| info.type_id == type_id)
+            .map(|(_, stats)| *stats)
+    }
+
+    /// Returns the sum of the [`MemoryStats`] of all registered components in
+    /// the subtree.
+    pub fn total_components(&self) -> MemoryStats {
+        self.total_for_kind(TypeKind::Component)
+    }
+
+    /// Returns the sum of the [`MemoryStats`] of all registered assets that
+    /// are referenced by the subtree.
+    ///
+    /// Each asset is counted once, no matter how many entities reference it.
+    pub fn total_assets(&self) -> MemoryStats {
+        self.total_for_kind(TypeKind::Asset)
+    }
+
+    /// Returns the sum of all of the [`MemoryStats`] in the subtree.
+    pub fn total(&self) -> MemoryStats {
+        self.types.iter().map(|(_, stats)| *stats).sum()
+    }
+
+    fn total_for_kind(&self, kind: TypeKind) -> MemoryStats {
+        self.types
+            .iter()
+            .filter(|(info, _)| info.kind == kind)
+            .map(|(_, stats)| *stats)
+            .sum()
+    }
+}
+
+impl fmt::Display for SubtreeMemory {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "Memory usage of the {} entities under {:?}:",
+            self.entity_count, self.root
+        )?;
+
+        for (info, stats) in self.types.iter() {
+            writeln!(f, "{}: {stats}", info.short_name)?;
+        }
+
+        write!(f, "Total: {}", self.total())
+    }
+}
+
+/***************************************************************************************************
+
+                             dMMMMMMP dMMMMMP .dMMMb dMMMMMMP .dMMMb
+                               dMP   dMP     dMP" VP   dMP   dMP" VP
+                              dMP   dMMMP    VMMMb    dMP    VMMMb
+                             dMP   dMP     dP .dMP   dMP   dP .dMP
+                            dMP   dMMMMMP  VMMMP"   dMP    VMMMP"
+
+***************************************************************************************************/
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use bevy::{ecs::component::Component, prelude::BuildWorldChildren, prelude::World};
+
+    use crate::{DataSizeEstimator, MemoryUsage};
+
+    #[derive(Component)]
+    struct Buffer(Vec<u8>);
+
+    #[derive(Component)]
+    struct Name(String);
+
+    #[derive(Default)]
+    struct BufferEstimator;
+
+    impl DataSizeEstimator<Buffer> for BufferEstimator {
+        const IS_DYNAMIC: bool = true;
+
+        fn estimate_heap_size(&self, value: &Buffer) -> usize {
+            value.0.len()
+        }
+    }
+
+    #[derive(Default)]
+    struct NameEstimator;
+
+    impl DataSizeEstimator<Name> for NameEstimator {
+        const IS_DYNAMIC: bool = true;
+
+        fn estimate_heap_size(&self, value: &Name) -> usize {
+            value.0.len()
+        }
+    }
+
+    fn memory_usage() -> MemoryUsage {
+        let mut memory_usage = MemoryUsage::default();
+
+        memory_usage.register_type_with_kind::<Buffer>(TypeKind::Component);
+        memory_usage.register_component_estimator::<Buffer, BufferEstimator>();
+        memory_usage.register_type_with_kind::<Name>(TypeKind::Component);
+        memory_usage.register_component_estimator::<Name, NameEstimator>();
+
+        memory_usage
+    }
+
+    #[test]
+    fn aggregates_parent_and_children_largest_first() {
+        let memory_usage = memory_usage();
+
+        let mut world = World::new();
+        let first = world
+            .spawn()
+            .insert(Buffer(vec![0; 100]))
+            .insert(Name("first".to_string()))
+            .id();
+        let second = world.spawn().insert(Buffer(vec![0; 50])).id();
+        let parent = world
+            .spawn()
+            .insert(Name("parent".to_string()))
+            .push_children(&[first, second])
+            .id();
+
+        let subtree = memory_usage.measure_subtree(&world, parent, false).unwrap();
+
+        assert_eq!(subtree.root(), parent);
+        assert_eq!(subtree.entity_count(), 3);
+
+        let names: Vec<_> = subtree
+            .types()
+            .iter()
+            .map(|(info, _)| info.short_name.as_str())
+            .collect();
+        assert_eq!(names, vec!["Buffer", "Name"]);
+
+        let buffers = subtree.get::<Buffer>().unwrap();
+        assert_eq!(buffers.count, 2);
+        assert_eq!(buffers.total_heap_bytes, 150);
+
+        let names = subtree.get::<Name>().unwrap();
+        assert_eq!(names.count, 2);
+        assert_eq!(names.total_heap_bytes, 11);
+
+        assert_eq!(subtree.total(), buffers + names);
+        assert_eq!(subtree.total_components(), subtree.total());
+        assert_eq!(subtree.total_assets(), MemoryStats::default());
+    }
+
+    #[test]
+    fn measures_only_the_given_subtree() {
+        let memory_usage = memory_usage();
+
+        let mut world = World::new();
+        let child = world.spawn().insert(Buffer(vec![0; 100])).id();
+        let parent = world
+            .spawn()
+            .insert(Buffer(vec![0; 10]))
+            .push_children(&[child])
+            .id();
+
+        let subtree = memory_usage.measure_subtree(&world, child, false).unwrap();
+        assert_eq!(subtree.entity_count(), 1);
+        assert_eq!(subtree.get::<Buffer>().unwrap().total_heap_bytes, 100);
+
+        world.despawn(parent);
+        world.despawn(child);
+        assert!(memory_usage.measure_subtree(&world, child, false).is_none());
+    }
+}
@@ -0,0 +1,197 @@
+use std::time::Duration;
+
+use crate::MemoryStats;
+
+/// A single timestamped [`MemoryStats`] sample.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct MemorySample {
+    /// The frame during which the stats were measured.
+    pub frame: u64,
+
+    /// The time at which the sample was recorded, relative to when the
+    /// [`MemoryUsagePlugin`] was added.
+    ///
+    /// [`MemoryUsagePlugin`]: crate::MemoryUsagePlugin
+    pub elapsed: Duration,
+
+    /// The measured stats.
+    pub stats: MemoryStats,
+}
+
+/// The most recent [`MemorySample`]s of a single type, oldest first.
+///
+/// The number of samples that are kept is controlled by
+/// [`MemoryConfig::history_length`].
+///
+/// [`MemoryConfig::history_length`]: crate::MemoryConfig::history_length
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct MemoryHistory {
+    samples: Vec<MemorySample>,
+}
+
+impl MemoryHistory {
+    pub(crate) fn new(samples: Vec<MemorySample>) -> Self {
+        Self { samples }
+    }
+
+    /// Returns the samples, oldest first.
+    #[inline]
+    pub fn samples(&self) -> &[MemorySample] {
+        &self.samples
+    }
+
+    /// Returns the most recent sample.
+    #[inline]
+    pub fn latest(&self) -> Option<&MemorySample> {
+        self.samples.last()
+    }
+
+    /// Returns the minimum of each field of [`MemoryStats`] over all samples.
+    ///
+    /// Returns `None` if there are no samples.
+    pub fn min(&self) -> Option<MemoryStats> {
+        self.fold_fields(usize::min)
+    }
+
+    /// Returns the maximum of each field of [`MemoryStats`] over all samples.
+    ///
+    /// Returns `None` if there are no samples.
+    pub fn max(&self) -> Option<MemoryStats> {
+        self.fold_fields(usize::max)
+    }
+
+    /// Returns the mean of each field of [`MemoryStats`] over all samples,
+    /// rounded down.
+    ///
+    /// Returns `None` if there are no samples.
+    pub fn mean(&self) -> Option<MemoryStats> {
+        let len = self.samples.len();
+        if len == 0 {
+            return None;
+        }
+
+        let sum = self.samples.iter().fold([0u128; 4], |sum, sample| {
+            [
+                sum[0] + sample.stats.count as u128,
+                sum[1] + sample.stats.total_stack_bytes as u128,
+                sum[2] + sample.stats.total_heap_bytes as u128,
+                sum[3] + sample.stats.total_device_bytes as u128,
+            ]
+        });
+
+        Some(MemoryStats {
+            count: (sum[0] / len as u128) as usize,
+            total_stack_bytes: (sum[1] / len as u128) as usize,
+            total_heap_bytes: (sum[2] / len as u128) as usize,
+            total_device_bytes: (sum[3] / len as u128) as usize,
+        })
+    }
+
+    fn fold_fields(&self, f: fn(usize, usize) -> usize) -> Option<MemoryStats> {
+        let (first, rest) = self.samples.split_first()?;
+
+        Some(rest.iter().fold(first.stats, |acc, sample| MemoryStats {
+            count: f(acc.count, sample.stats.count),
+            total_stack_bytes: f(acc.total_stack_bytes, sample.stats.total_stack_bytes),
+            total_heap_bytes: f(acc.total_heap_bytes, sample.stats.total_heap_bytes),
+            total_device_bytes: f(acc.total_device_bytes, sample.stats.total_device_bytes),
+        }))
+    }
+}
+
+/***************************************************************************************************
+
+                             dMMMMMMP dMMMMMP .dMMMb dMMMMMMP .dMMMb
+                               dMP   dMP     dMP" VP   dMP   dMP" VP
+                              dMP   dMMMP    VMMMb    dMP    VMMMb
+                             dMP   dMP     dP .dMP   dMP   dP .dMP
+                            dMP   dMMMMMP  VMMMP"   dMP    VMMMP"
+
+***************************************************************************************************/
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::{MemoryUsage, TypeKind};
+
+    struct Apple;
+
+    fn stats(count: usize, total_heap_bytes: usize) -> MemoryStats {
+        MemoryStats {
+            count,
+            total_stack_bytes: 8 * count,
+            total_heap_bytes,
+            total_device_bytes: 0,
+        }
+    }
+
+    fn history(stats: &[MemoryStats]) -> MemoryHistory {
+        MemoryHistory::new(
+            stats
+                .iter()
+                .zip(1..)
+                .map(|(stats, frame)| MemorySample {
+                    frame,
+                    elapsed: Duration::ZERO,
+                    stats: *stats,
+                })
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn aggregates_each_field_separately() {
+        let history = history(&[stats(1, 500), stats(4, 100), stats(4, 301)]);
+
+        assert_eq!(history.min(), Some(stats(1, 100)));
+        assert_eq!(history.max(), Some(stats(4, 500)));
+        assert_eq!(history.latest().unwrap().stats, stats(4, 301));
+
+        // The heap bytes round down from 901 / 3.
+        assert_eq!(history.mean(), Some(stats(3, 300)));
+    }
+
+    #[test]
+    fn empty_history_has_no_aggregates() {
+        let history = MemoryHistory::default();
+
+        assert_eq!(history.latest(), None);
+        assert_eq!(history.min(), None);
+        assert_eq!(history.max(), None);
+        assert_eq!(history.mean(), None);
+    }
+
+    fn recorded_frames(memory_usage: &MemoryUsage) -> Vec<u64> {
+        memory_usage
+            .get_history::<Apple>()
+            .unwrap()
+            .samples()
+            .iter()
+            .map(|sample| sample.frame)
+            .collect()
+    }
+
+    #[test]
+    fn keeps_only_the_most_recent_samples() {
+        let mut memory_usage = MemoryUsage::default();
+        memory_usage.register_type_with_kind::<Apple>(TypeKind::Component);
+
+        for frame in 1..=5 {
+            memory_usage.begin_frame(frame, true);
+            memory_usage.update_stats_fast::<Apple>(stats(1, frame as usize));
+            memory_usage.record_history(3);
+        }
+
+        assert_eq!(recorded_frames(&memory_usage), [3, 4, 5]);
+
+        let history = memory_usage.get_history::<Apple>().unwrap();
+        assert_eq!(history.latest().unwrap().stats, stats(1, 5));
+
+        // Shrinking the history length drops the oldest samples.
+        memory_usage.begin_frame(6, true);
+        memory_usage.record_history(2);
+
+        assert_eq!(recorded_frames(&memory_usage), [5, 6]);
+    }
+}
@@ -0,0 +1,141 @@
+use std::fmt;
+
+use bevy::{asset::HandleId, ecs::entity::Entity};
+
+use crate::MemoryStats;
+
+/// Identifies a single instance of a registered type.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum InstanceId {
+    /// A component on the given entity.
+    Entity(Entity),
+
+    /// The asset (or render asset) with the given handle.
+    Asset(HandleId),
+}
+
+impl From<Entity> for InstanceId {
+    #[inline]
+    fn from(entity: Entity) -> Self {
+        Self::Entity(entity)
+    }
+}
+
+impl From<HandleId> for InstanceId {
+    #[inline]
+    fn from(handle_id: HandleId) -> Self {
+        Self::Asset(handle_id)
+    }
+}
+
+impl fmt::Display for InstanceId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Entity(entity) => write!(f, "entity {entity:?}"),
+            Self::Asset(handle_id) => write!(f, "asset {handle_id:?}"),
+        }
+    }
+}
+
+/// The [`MemoryStats`] of a single instance of a registered type.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct InstanceStats {
+    /// The instance that was measured.
+    pub id: InstanceId,
+
+    /// The stats of the instance.
+    pub stats: MemoryStats,
+}
+
+/// Returns the `n` largest of the given instances by total size, including
+/// device memory, largest first.
+pub(crate) fn largest_instances<I>(instances: I, n: usize) -> Vec<InstanceStats>
+where
+    I: IntoIterator<Item = InstanceStats>,
+{
+    let mut instances: Vec<InstanceStats> = instances.into_iter().collect();
+
+    let by_size_descending = |a: &InstanceStats, b: &InstanceStats| {
+        b.stats
+            .total_including_device()
+            .cmp(&a.stats.total_including_device())
+    };
+
+    if n == 0 {
+        return Vec::new();
+    } else if instances.len() > n {
+        instances.select_nth_unstable_by(n - 1, by_size_descending);
+        instances.truncate(n);
+    }
+
+    instances.sort_by(by_size_descending);
+
+    instances
+}
+
+/***************************************************************************************************
+
+                             dMMMMMMP dMMMMMP .dMMMb dMMMMMMP .dMMMb
+                               dMP   dMP     dMP" VP   dMP   dMP" VP
+                              dMP   dMMMP    VMMMb    dMP    VMMMb
+                             dMP   dMP     dP .dMP   dMP   dP .dMP
+                            dMP   dMMMMMP  VMMMP"   dMP    VMMMP"
+
+***************************************************************************************************/
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn instance(id: u32, total_heap_bytes: usize, total_device_bytes: usize) -> InstanceStats {
+        InstanceStats {
+            id: Entity::from_raw(id).into(),
+            stats: MemoryStats {
+                count: 1,
+                total_stack_bytes: 8,
+                total_heap_bytes,
+                total_device_bytes,
+            },
+        }
+    }
+
+    fn instances() -> Vec<InstanceStats> {
+        vec![
+            instance(0, 100, 0),
+            instance(1, 300, 0),
+            instance(2, 50, 400),
+            instance(3, 200, 0),
+        ]
+    }
+
+    fn ids(instances: &[InstanceStats]) -> Vec<InstanceId> {
+        instances.iter().map(|instance| instance.id).collect()
+    }
+
+    fn entities(ids: &[u32]) -> Vec<InstanceId> {
+        ids.iter().map(|id| Entity::from_raw(*id).into()).collect()
+    }
+
+    #[test]
+    fn orders_largest_first_including_device_bytes() {
+        assert_eq!(ids(&largest_instances(instances(), 2)), entities(&[2, 1]));
+    }
+
+    #[test]
+    fn returns_all_instances_if_there_are_at_most_n() {
+        assert_eq!(
+            ids(&largest_instances(instances(), 4)),
+            entities(&[2, 1, 3, 0])
+        );
+        assert_eq!(
+            ids(&largest_instances(instances(), 10)),
+            entities(&[2, 1, 3, 0])
+        );
+    }
+
+    #[test]
+    fn returns_nothing_for_zero() {
+        assert!(largest_instances(instances(), 0).is_empty());
+        assert!(largest_instances(Vec::new(), 3).is_empty());
+    }
+}
@@ -34,6 +34,7 @@
 //!         count,
 //!         total_stack_bytes,
 //!         total_heap_bytes,
+//!         ..
 //!     } = memory_usage.get_stats::<MyComponent>().unwrap();
 //!
 //!     println!("MyComponent count: {count}");
@@ -48,22 +49,48 @@ pub use datasize;
 pub use datasize::DataSize;
 
 pub mod app_ext;
+mod asset_path;
 pub mod builtins;
 mod config;
+mod deep;
+mod diff;
+mod distribution;
+mod entity;
 pub mod estimator;
+mod hierarchy;
+mod history;
+mod instance;
+mod peaks;
 mod plugin;
+mod report;
 mod resource;
+mod shared;
 mod stats;
 pub mod systems;
+mod type_info;
 
 #[doc(inline)]
 pub use app_ext::RegisterSizedTypes;
-pub use config::MemoryConfig;
+pub use asset_path::AssetPathReport;
+pub use config::{
+    ComponentTrackingMode, EstimatorConfigValue, EstimatorConfigs, MemoryConfig, SamplingPolicy,
+};
+pub use deep::{DeepMemory, HandleAttribution, HandleReferences, HandleVisitor};
+pub use diff::{MemoryReportDiff, MemoryStatsDelta, TypeChange, TypeDiff};
+pub use distribution::SizeDistribution;
+pub use entity::EntityMemory;
 #[doc(inline)]
 pub use estimator::DataSizeEstimator;
+pub use hierarchy::SubtreeMemory;
+pub use history::{MemoryHistory, MemorySample};
+pub use instance::{InstanceId, InstanceStats};
+pub use peaks::{MemoryPeaks, Peak};
 pub use plugin::MemoryUsagePlugin;
+pub use report::MemoryReport;
 pub use resource::MemoryUsage;
+pub use shared::{SharedAllocations, VisitSharedAllocations};
 pub use stats::MemoryStats;
+pub use type_info::{TypeInfo, TypeKind};
 
 #[allow(missing_docs)]
 pub mod prelude {
@@ -0,0 +1,177 @@
+use crate::MemoryStats;
+
+/// The highest value observed for a single quantity, along with the frame at
+/// which it was observed.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Peak {
+    /// The highest observed value.
+    pub value: usize,
+
+    /// The frame during which the value was observed.
+    ///
+    /// See [`MemoryUsage::frame`][crate::MemoryUsage::frame].
+    pub frame: u64,
+}
+
+impl Peak {
+    #[inline]
+    fn update(&mut self, value: usize, frame: u64) {
+        if value > self.value {
+            self.value = value;
+            self.frame = frame;
+        }
+    }
+}
+
+/// The high-water marks of the [`MemoryStats`] of a single type.
+///
+/// Each field is tracked independently, so the peaks may have occurred at
+/// different frames.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct MemoryPeaks {
+    /// The peak of [`MemoryStats::count`].
+    pub count: Peak,
+
+    /// The peak of [`MemoryStats::total_stack_bytes`].
+    pub total_stack_bytes: Peak,
+
+    /// The peak of [`MemoryStats::total_heap_bytes`].
+    pub total_heap_bytes: Peak,
+
+    /// The peak of [`MemoryStats::total_bytes`].
+    pub total_bytes: Peak,
+
+    /// The peak of [`MemoryStats::total_device_bytes`].
+    pub total_device_bytes: Peak,
+}
+
+impl MemoryPeaks {
+    /// Returns peaks whose values are equal to the given stats.
+    pub(crate) fn from_stats(stats: MemoryStats, frame: u64) -> Self {
+        let peak = |value| Peak { value, frame };
+
+        Self {
+            count: peak(stats.count),
+            total_stack_bytes: peak(stats.total_stack_bytes),
+            total_heap_bytes: peak(stats.total_heap_bytes),
+            total_bytes: peak(stats.total_bytes()),
+            total_device_bytes: peak(stats.total_device_bytes),
+        }
+    }
+
+    /// Raises the peaks to the given stats where they are higher.
+    pub(crate) fn update(&mut self, stats: MemoryStats, frame: u64) {
+        self.count.update(stats.count, frame);
+        self.total_stack_bytes
+            .update(stats.total_stack_bytes, frame);
+        self.total_heap_bytes.update(stats.total_heap_bytes, frame);
+        self.total_bytes.update(stats.total_bytes(), frame);
+        self.total_device_bytes
+            .update(stats.total_device_bytes, frame);
+    }
+}
+
+/***************************************************************************************************
+
+                             dMMMMMMP dMMMMMP .dMMMb dMMMMMMP .dMMMb
+                               dMP   dMP     dMP" VP   dMP   dMP" VP
+                              dMP   dMMMP    VMMMb    dMP    VMMMb
+                             dMP   dMP     dP .dMP   dMP   dP .dMP
+                            dMP   dMMMMMP  VMMMP"   dMP    VMMMP"
+
+***************************************************************************************************/
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::{MemoryUsage, TypeKind};
+
+    struct Apple;
+    struct Banana;
+
+    fn stats(count: usize, total_heap_bytes: usize) -> MemoryStats {
+        MemoryStats {
+            count,
+            total_stack_bytes: 8 * count,
+            total_heap_bytes,
+            total_device_bytes: 0,
+        }
+    }
+
+    fn memory_usage(frames: &[MemoryStats]) -> MemoryUsage {
+        let mut memory_usage = MemoryUsage::default();
+        memory_usage.register_type_with_kind::<Apple>(TypeKind::Component);
+        memory_usage.register_type_with_kind::<Banana>(TypeKind::Component);
+
+        for (stats, frame) in frames.iter().zip(1..) {
+            memory_usage.begin_frame(frame, true);
+            memory_usage.update_stats_fast::<Apple>(*stats);
+            memory_usage.update_stats_fast::<Banana>(*stats);
+        }
+
+        memory_usage
+    }
+
+    #[test]
+    fn records_the_frame_of_each_peak() {
+        let memory_usage = memory_usage(&[stats(1, 100), stats(2, 300), stats(3, 200)]);
+
+        let peaks = memory_usage.get_peaks::<Apple>().unwrap();
+        assert_eq!(peaks.count, Peak { value: 3, frame: 3 });
+        assert_eq!(
+            peaks.total_stack_bytes,
+            Peak {
+                value: 24,
+                frame: 3
+            }
+        );
+        assert_eq!(
+            peaks.total_heap_bytes,
+            Peak {
+                value: 300,
+                frame: 2
+            }
+        );
+        assert_eq!(
+            peaks.total_bytes,
+            Peak {
+                value: 316,
+                frame: 2
+            }
+        );
+        assert_eq!(peaks.total_device_bytes, Peak::default());
+
+        assert_eq!(memory_usage.get_peaks::<String>(), None);
+    }
+
+    #[test]
+    fn resets_peaks_to_current_stats() {
+        let memory_usage = memory_usage(&[stats(2, 300), stats(1, 100)]);
+
+        memory_usage.reset_peaks::<Apple>();
+        assert_eq!(
+            memory_usage.get_peaks::<Apple>(),
+            Some(MemoryPeaks::from_stats(stats(1, 100), 2))
+        );
+        assert_eq!(
+            memory_usage.get_peaks::<Banana>().unwrap().total_heap_bytes,
+            Peak {
+                value: 300,
+                frame: 1
+            }
+        );
+
+        memory_usage.begin_frame(3, true);
+        memory_usage.update_stats_fast::<Apple>(stats(1, 50));
+        memory_usage.update_stats_fast::<Banana>(stats(1, 50));
+
+        memory_usage.reset_all_peaks();
+        for peaks in [
+            memory_usage.get_peaks::<Apple>(),
+            memory_usage.get_peaks::<Banana>(),
+        ] {
+            assert_eq!(peaks, Some(MemoryPeaks::from_stats(stats(1, 50), 3)));
+        }
+    }
+}
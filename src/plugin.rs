@@ -1,4 +1,4 @@
-use bevy::app::Plugin;
+use bevy::app::{CoreStage, Plugin};
 
 #[cfg(feature = "bevy_render")]
 use bevy::{
@@ -6,7 +6,7 @@ use bevy::{
     render::{RenderApp, RenderStage},
 };
 
-use crate::{MemoryConfig, MemoryUsage};
+use crate::{systems, MemoryConfig, MemoryUsage};
 
 /// Adds memory usage tracking to Bevy apps.
 ///
@@ -28,6 +28,8 @@ impl Plugin for MemoryUsagePlugin {
         app.init_resource::<MemoryConfig>();
         app.init_resource::<MemoryUsage>();
 
+        app.add_system_to_stage(CoreStage::First, systems::begin_frame);
+
         #[cfg(feature = "bevy_render")]
         {
             let memory_config = app.world.get_resource::<MemoryConfig>().unwrap().clone();
@@ -47,8 +49,18 @@ impl Plugin for MemoryUsagePlugin {
 
 /// This system copies the [`MemoryConfig`] from the main world to the render
 /// sub-world.
+///
+/// This includes the per-type settings, so that systems in the render world
+/// honour them as well.
+///
+/// The config is only copied when it changed, as it may hold arbitrary
+/// estimator settings that are not cheap to clone.
 #[cfg(feature = "bevy_render")]
 fn synchronize_configs(memory_config: Res<MemoryConfig>, mut render_commands: Commands) {
+    if !memory_config.is_changed() {
+        return;
+    }
+
     let clone = memory_config.clone();
 
     render_commands.insert_resource(clone);
@@ -0,0 +1,219 @@
+use std::{
+    any::{Any, TypeId},
+    fmt,
+    time::SystemTime,
+};
+
+use crate::{diff, MemoryReportDiff, MemoryStats, TypeInfo, TypeKind};
+
+/// A snapshot of the [`MemoryStats`] of every type registered with the
+/// [`MemoryUsage`] resource at a single point in time.
+///
+/// Use [`MemoryUsage::snapshot`] to create one.
+///
+/// [`MemoryUsage`]: crate::MemoryUsage
+/// [`MemoryUsage::snapshot`]: crate::MemoryUsage::snapshot
+#[derive(Debug, Clone)]
+pub struct MemoryReport {
+    frame: u64,
+    timestamp: SystemTime,
+    types: Vec<(TypeInfo, MemoryStats)>,
+}
+
+impl MemoryReport {
+    pub(crate) fn new(
+        frame: u64,
+        timestamp: SystemTime,
+        types: Vec<(TypeInfo, MemoryStats)>,
+    ) -> Self {
+        Self {
+            frame,
+            timestamp,
+            types,
+        }
+    }
+
+    /// Returns the frame during which the snapshot was taken.
+    ///
+    /// See [`MemoryUsage::frame`][crate::MemoryUsage::frame].
+    #[inline]
+    pub fn frame(&self) -> u64 {
+        self.frame
+    }
+
+    /// Returns the time at which the snapshot was taken.
+    #[inline]
+    pub fn timestamp(&self) -> SystemTime {
+        self.timestamp
+    }
+
+    /// Returns the [`TypeInfo`] and [`MemoryStats`] of every registered type.
+    ///
+    /// The types are ordered by their [`TypeKind`] and then by their short
+    /// name.
+    #[inline]
+    pub fn types(&self) -> &[(TypeInfo, MemoryStats)] {
+        &self.types
+    }
+
+    /// Returns the [`MemoryStats`] for the given type.
+    ///
+    /// Returns `None` if the type was not registered.
+    pub fn get<T>(&self) -> Option<MemoryStats>
+    where
+        T: Any,
+    {
+        self.get_by_type_id(TypeId::of::<T>())
+    }
+
+    /// Like [`get`][Self::get] but takes a [`TypeId`].
+    pub fn get_by_type_id(&self, type_id: TypeId) -> Option<MemoryStats> {
+        self.types
+            .iter()
+            .find(|(info, _)| info.type_id == type_id)
+            .map(|(_, stats)| *stats)
+    }
+
+    /// Returns the sum of the [`MemoryStats`] of all types of the given kind.
+    pub fn total_for_kind(&self, kind: TypeKind) -> MemoryStats {
+        self.types
+            .iter()
+            .filter(|(info, _)| info.kind == kind)
+            .map(|(_, stats)| *stats)
+            .sum()
+    }
+
+    /// Returns the sum of the [`MemoryStats`] of all component types.
+    #[inline]
+    pub fn total_components(&self) -> MemoryStats {
+        self.total_for_kind(TypeKind::Component)
+    }
+
+    /// Returns the sum of the [`MemoryStats`] of all resource types.
+    #[inline]
+    pub fn total_resources(&self) -> MemoryStats {
+        self.total_for_kind(TypeKind::Resource)
+    }
+
+    /// Returns the sum of the [`MemoryStats`] of all asset types.
+    #[inline]
+    pub fn total_assets(&self) -> MemoryStats {
+        self.total_for_kind(TypeKind::Asset)
+    }
+
+    /// Returns the sum of the [`MemoryStats`] of all render asset types.
+    #[inline]
+    pub fn total_render_assets(&self) -> MemoryStats {
+        self.total_for_kind(TypeKind::RenderAsset)
+    }
+
+    /// Returns the sum of the [`MemoryStats`] of all registered types.
+    pub fn total(&self) -> MemoryStats {
+        self.types.iter().map(|(_, stats)| *stats).sum()
+    }
+
+    /// Returns the changes in memory usage from `self` to the given `later`
+    /// report.
+    ///
+    /// Types that only have instances in one of the two reports are flagged as
+    /// having appeared or disappeared (see [`TypeChange`]).
+    ///
+    /// [`TypeChange`]: crate::TypeChange
+    pub fn diff(&self, later: &MemoryReport) -> MemoryReportDiff {
+        diff::diff_reports(self, later)
+    }
+}
+
+impl fmt::Display for MemoryReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Memory usage at frame {}:", self.frame)?;
+
+        for (info, stats) in self.types.iter() {
+            writeln!(f, "{}: {stats}", info.short_name)?;
+        }
+
+        write!(f, "Total: {}", self.total())
+    }
+}
+
+/***************************************************************************************************
+
+                             dMMMMMMP dMMMMMP .dMMMb dMMMMMMP .dMMMb
+                               dMP   dMP     dMP" VP   dMP   dMP" VP
+                              dMP   dMMMP    VMMMb    dMP    VMMMb
+                             dMP   dMP     dP .dMP   dMP   dP .dMP
+                            dMP   dMMMMMP  VMMMP"   dMP    VMMMP"
+
+***************************************************************************************************/
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::MemoryUsage;
+
+    struct Apple;
+    struct Banana;
+    struct Cherry;
+    struct Date;
+
+    fn stats(count: usize, total_heap_bytes: usize) -> MemoryStats {
+        MemoryStats {
+            count,
+            total_stack_bytes: 8 * count,
+            total_heap_bytes,
+            total_device_bytes: 0,
+        }
+    }
+
+    fn snapshot() -> MemoryReport {
+        let mut memory_usage = MemoryUsage::default();
+        memory_usage.register_type_with_kind::<Date>(TypeKind::Asset);
+        memory_usage.register_type_with_kind::<Cherry>(TypeKind::Component);
+        memory_usage.register_type_with_kind::<Banana>(TypeKind::Resource);
+        memory_usage.register_type_with_kind::<Apple>(TypeKind::Component);
+
+        memory_usage.begin_frame(7, true);
+        memory_usage.update_stats_fast::<Apple>(stats(1, 100));
+        memory_usage.update_stats_fast::<Banana>(stats(1, 200));
+        memory_usage.update_stats_fast::<Cherry>(stats(2, 300));
+
+        memory_usage.snapshot()
+    }
+
+    #[test]
+    fn snapshot_holds_stats_of_all_types() {
+        let report = snapshot();
+
+        assert_eq!(report.frame(), 7);
+        assert_eq!(report.get::<Apple>(), Some(stats(1, 100)));
+        assert_eq!(report.get::<Date>(), Some(MemoryStats::default()));
+        assert_eq!(report.get::<String>(), None);
+    }
+
+    #[test]
+    fn orders_types_by_kind_then_name() {
+        let report = snapshot();
+
+        let names: Vec<_> = report
+            .types()
+            .iter()
+            .map(|(info, _)| info.short_name.as_str())
+            .collect();
+        assert_eq!(names, ["Apple", "Cherry", "Banana", "Date"]);
+    }
+
+    #[test]
+    fn sums_stats_by_kind() {
+        let report = snapshot();
+
+        assert_eq!(report.total_components(), stats(3, 400));
+        assert_eq!(report.total_resources(), stats(1, 200));
+        assert_eq!(report.total_assets(), MemoryStats::default());
+        assert_eq!(
+            report.total_for_kind(TypeKind::Custom),
+            MemoryStats::default()
+        );
+        assert_eq!(report.total(), stats(4, 600));
+    }
+}
@@ -1,12 +1,32 @@
 use std::{
     any::{Any, TypeId},
-    sync::Arc,
+    collections::VecDeque,
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Arc,
+    },
+    time::{Instant, SystemTime},
 };
 
-use bevy::utils::HashMap;
-use parking_lot::RwLock;
+use bevy::{
+    asset::{Asset, HandleId},
+    ecs::{component::Component, entity::Entity, world::World},
+    transform::components::Children,
+    utils::{HashMap, HashSet},
+};
+use parking_lot::{Mutex, RwLock};
 
-use crate::stats::{MemoryStats, MemoryStatsInternal};
+use crate::{
+    asset_path::AssetPathReport,
+    deep::{component_references, AssetReferencesFns, ComponentReferencesFn, SharedStats},
+    entity::{estimate_component, AssetEstimatorFns, ComponentEstimatorFn, EstimatorState},
+    estimator::FromConfig,
+    stats::{MemoryStats, MemoryStatsInternal},
+    DataSizeEstimator, DeepMemory, EntityMemory, HandleAttribution, HandleReferences,
+    InstanceStats, MemoryConfig, MemoryHistory, MemoryPeaks, MemoryReport, MemorySample,
+    SizeDistribution, SubtreeMemory, TypeInfo, TypeKind,
+};
 
 /// Stores memory usage statistics for registered data types.
 #[derive(Debug, Default, Clone)]
@@ -15,8 +35,22 @@ pub struct MemoryUsage {
 }
 
 impl MemoryUsage {
-    /// Registers the given type with the usage tracker.
+    /// Registers the given type with the usage tracker as a
+    /// [`TypeKind::Custom`] type.
+    ///
+    /// Registering a type that is already registered has no effect.
     pub fn register_type<T>(&mut self)
+    where
+        T: Any,
+    {
+        self.register_type_with_kind::<T>(TypeKind::Custom);
+    }
+
+    /// Registers the given type with the usage tracker as the given
+    /// [`TypeKind`].
+    ///
+    /// Registering a type that is already registered has no effect.
+    pub fn register_type_with_kind<T>(&mut self, kind: TypeKind)
     where
         T: Any,
     {
@@ -25,7 +59,97 @@ impl MemoryUsage {
         self.inner
             .write()
             .datasizes
-            .insert(type_id, Default::default());
+            .entry(type_id)
+            .or_insert_with(|| TypeEntry::new(TypeInfo::of::<T>(kind)));
+    }
+
+    /// Records the [`DataSizeEstimator`] to use for the given component type
+    /// in [`measure_entity`][Self::measure_entity].
+    ///
+    /// The type must already be registered.
+    pub fn register_component_estimator<T, E>(&mut self)
+    where
+        T: Any + Component,
+        E: DataSizeEstimator<T> + FromConfig + Send + 'static,
+    {
+        let type_id = TypeId::of::<T>();
+
+        let mut inner = self.inner.write();
+
+        let entry = inner
+            .datasizes
+            .get_mut(&type_id)
+            .expect("Memory usage not tracked for this type. Did you forget to register the type?");
+
+        entry.component_estimator = Some(estimate_component::<T, E>);
+        *entry.component_estimator_state.get_mut() = None;
+    }
+
+    /// Records the [`DataSizeEstimator`] to use for the given asset type in
+    /// [`measure_subtree`][Self::measure_subtree].
+    ///
+    /// The type must already be registered.
+    pub fn register_asset_estimator<T, E>(&mut self)
+    where
+        T: Any + Asset,
+        E: DataSizeEstimator<T> + FromConfig + Send + 'static,
+    {
+        let type_id = TypeId::of::<T>();
+
+        let mut inner = self.inner.write();
+
+        let entry = inner
+            .datasizes
+            .get_mut(&type_id)
+            .expect("Memory usage not tracked for this type. Did you forget to register the type?");
+
+        entry.asset_estimator = Some(AssetEstimatorFns::new::<T, E>());
+        *entry.asset_estimator_state.get_mut() = None;
+    }
+
+    /// Records the [`HandleReferences`] to use for the given component type
+    /// in [`measure_entity_deep`][Self::measure_entity_deep].
+    ///
+    /// The type must already be registered.
+    pub fn register_component_references<T, R>(&mut self)
+    where
+        T: Any + Component,
+        R: HandleReferences<T> + FromConfig + Send + 'static,
+    {
+        let type_id = TypeId::of::<T>();
+
+        let mut inner = self.inner.write();
+
+        let entry = inner
+            .datasizes
+            .get_mut(&type_id)
+            .expect("Memory usage not tracked for this type. Did you forget to register the type?");
+
+        entry.component_references = Some(component_references::<T, R>);
+        *entry.component_references_state.get_mut() = None;
+    }
+
+    /// Records the [`HandleReferences`] to use for the given asset type in
+    /// [`measure_entity_deep`][Self::measure_entity_deep] and
+    /// [`measure_asset_deep`][Self::measure_asset_deep].
+    ///
+    /// The type must already be registered.
+    pub fn register_asset_references<T, R>(&mut self)
+    where
+        T: Any + Asset,
+        R: HandleReferences<T> + FromConfig + Send + 'static,
+    {
+        let type_id = TypeId::of::<T>();
+
+        let mut inner = self.inner.write();
+
+        let entry = inner
+            .datasizes
+            .get_mut(&type_id)
+            .expect("Memory usage not tracked for this type. Did you forget to register the type?");
+
+        entry.asset_references = Some(AssetReferencesFns::new::<T, R>());
+        *entry.asset_references_state.get_mut() = None;
     }
 
     /// Returns the most recent [`MemoryStats`] for the given type.
@@ -35,13 +159,282 @@ impl MemoryUsage {
     where
         T: Any,
     {
-        let type_id = TypeId::of::<T>();
+        self.get_stats_by_type_id(TypeId::of::<T>())
+    }
 
+    /// Like [`get_stats`][Self::get_stats] but takes a [`TypeId`].
+    pub fn get_stats_by_type_id(&self, type_id: TypeId) -> Option<MemoryStats> {
         self.inner
             .read()
             .datasizes
             .get(&type_id)
-            .map(MemoryStatsInternal::get)
+            .map(|entry| entry.stats.get())
+    }
+
+    /// Returns the [`TypeInfo`] that was recorded when the given type was
+    /// registered.
+    ///
+    /// Returns `None` if the type has not been registered.
+    pub fn get_type_info<T>(&self) -> Option<TypeInfo>
+    where
+        T: Any,
+    {
+        self.inner
+            .read()
+            .datasizes
+            .get(&TypeId::of::<T>())
+            .map(|entry| entry.info.clone())
+    }
+
+    /// Returns the [`TypeInfo`] and most recent [`MemoryStats`] of every
+    /// registered type.
+    ///
+    /// The types are ordered by their [`TypeKind`] and then by their short
+    /// name.
+    pub fn iter(&self) -> impl Iterator<Item = (TypeInfo, MemoryStats)> {
+        self.inner.read().all_stats().into_iter()
+    }
+
+    /// Returns a [`MemoryReport`] containing the most recent [`MemoryStats`]
+    /// of every registered type.
+    pub fn snapshot(&self) -> MemoryReport {
+        let inner = self.inner.read();
+
+        MemoryReport::new(
+            inner.frame.load(Ordering::Relaxed),
+            SystemTime::now(),
+            inner.all_stats(),
+        )
+    }
+
+    /// Returns the recorded [`MemoryHistory`] for the given type.
+    ///
+    /// The history will be empty unless [`MemoryConfig::history_length`] is
+    /// non-zero.
+    ///
+    /// Returns `None` if the type has not been registered.
+    ///
+    /// [`MemoryConfig::history_length`]: crate::MemoryConfig::history_length
+    pub fn get_history<T>(&self) -> Option<MemoryHistory>
+    where
+        T: Any,
+    {
+        self.inner
+            .read()
+            .datasizes
+            .get(&TypeId::of::<T>())
+            .map(|entry| MemoryHistory::new(entry.history.iter().copied().collect()))
+    }
+
+    /// Estimates the memory usage of each registered component on the given
+    /// entity, using the same [`DataSizeEstimator`]s that are used to update
+    /// the [`MemoryStats`] of the component types.
+    ///
+    /// Returns `None` if the entity does not exist.
+    pub fn measure_entity(&self, world: &World, entity: Entity) -> Option<EntityMemory> {
+        world.get_entity(entity)?;
+
+        let default_config = MemoryConfig::default();
+        let memory_config = world
+            .get_resource::<MemoryConfig>()
+            .unwrap_or(&default_config);
+
+        let inner = self.inner.read();
+        let config_generation = inner.config_generation.load(Ordering::Relaxed);
+
+        let components = inner
+            .datasizes
+            .values()
+            .filter_map(|entry| {
+                let stats =
+                    entry.estimate_component(world, entity, memory_config, config_generation)?;
+
+                Some((entry.info.clone(), stats))
+            })
+            .collect();
+
+        Some(EntityMemory::new(entity, components))
+    }
+
+    /// Estimates the memory usage of the registered components of the given
+    /// entity and all of its descendants, following [`Children`].
+    ///
+    /// If `include_assets` is `true`, the registered assets that are
+    /// referenced by a [`Handle`] component anywhere in the subtree are
+    /// included as well. Each asset is only counted once.
+    ///
+    /// Returns `None` if the entity does not exist.
+    ///
+    /// [`Handle`]: bevy::asset::Handle
+    pub fn measure_subtree(
+        &self,
+        world: &World,
+        root: Entity,
+        include_assets: bool,
+    ) -> Option<SubtreeMemory> {
+        world.get_entity(root)?;
+
+        let default_config = MemoryConfig::default();
+        let memory_config = world
+            .get_resource::<MemoryConfig>()
+            .unwrap_or(&default_config);
+
+        let inner = self.inner.read();
+        let config_generation = inner.config_generation.load(Ordering::Relaxed);
+
+        let mut totals: HashMap<TypeId, MemoryStats> = HashMap::default();
+        let mut seen_assets: HashSet<(TypeId, HandleId)> = HashSet::default();
+        let mut entity_count = 0;
+
+        let mut stack = vec![root];
+        while let Some(entity) = stack.pop() {
+            entity_count += 1;
+
+            for (type_id, entry) in inner.datasizes.iter() {
+                let stats = match (entry.component_estimator, entry.asset_estimator) {
+                    (Some(_), _) => {
+                        entry.estimate_component(world, entity, memory_config, config_generation)
+                    }
+                    (None, Some(asset)) if include_assets => (asset.handle_of)(world, entity)
+                        .filter(|handle_id| seen_assets.insert((*type_id, *handle_id)))
+                        .and_then(|handle_id| {
+                            entry.estimate_asset(world, handle_id, memory_config, config_generation)
+                        }),
+                    _ => None,
+                };
+
+                if let Some(stats) = stats {
+                    let total = totals.entry(*type_id).or_default();
+                    *total = *total + stats;
+                }
+            }
+
+            if let Some(children) = world.get::<Children>(entity) {
+                stack.extend(children.iter().copied());
+            }
+        }
+
+        let types = totals
+            .into_iter()
+            .map(|(type_id, stats)| (inner.datasizes[&type_id].info.clone(), stats))
+            .collect();
+
+        Some(SubtreeMemory::new(root, entity_count, types))
+    }
+
+    /// Estimates the memory usage of the registered components of the given
+    /// entity, together with the registered assets that it references.
+    ///
+    /// Assets are referenced by [`Handle`] components and by the handles found
+    /// by [`HandleReferences`] registered for the entity's components. The
+    /// handles held by referenced assets are followed as well. With
+    /// [`HandleAttribution::Full`], each asset is only counted once.
+    ///
+    /// With [`HandleAttribution::Proportional`], every entity and asset in the
+    /// world is visited to count the referrers of each asset, which can be
+    /// slow in large worlds.
+    ///
+    /// Returns `None` if the entity does not exist.
+    ///
+    /// [`Handle`]: bevy::asset::Handle
+    pub fn measure_entity_deep(
+        &self,
+        world: &World,
+        entity: Entity,
+        attribution: HandleAttribution,
+    ) -> Option<DeepMemory> {
+        world.get_entity(entity)?;
+
+        let default_config = MemoryConfig::default();
+        let memory_config = world
+            .get_resource::<MemoryConfig>()
+            .unwrap_or(&default_config);
+
+        let inner = self.inner.read();
+        let config_generation = inner.config_generation.load(Ordering::Relaxed);
+
+        let shallow = inner
+            .datasizes
+            .values()
+            .filter_map(|entry| {
+                entry.estimate_component(world, entity, memory_config, config_generation)
+            })
+            .sum();
+
+        let handles = inner.handles_of_entity(world, entity, memory_config);
+        let referenced = inner.referenced_assets(
+            world,
+            memory_config,
+            attribution,
+            handles,
+            HashSet::default(),
+        );
+
+        Some(DeepMemory::new(shallow, referenced))
+    }
+
+    /// Estimates the memory usage of the given asset, together with the
+    /// registered assets that it references through the handles found by its
+    /// [`HandleReferences`].
+    ///
+    /// See [`measure_entity_deep`][Self::measure_entity_deep] for details.
+    ///
+    /// Returns `None` if the asset type is not registered or the asset does
+    /// not exist.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use bevy::prelude::*;
+    /// # use bevy_datasize::{prelude::*, HandleAttribution};
+    /// fn print_material_size(world: &World, material: &Handle<StandardMaterial>) {
+    ///     let memory_usage = world.get_resource::<MemoryUsage>().unwrap();
+    ///
+    ///     let attribution = HandleAttribution::Full;
+    ///
+    ///     if let Some(memory) =
+    ///         memory_usage.measure_asset_deep::<StandardMaterial>(world, material, attribution)
+    ///     {
+    ///         println!("Material keeps alive: {}", memory.total());
+    ///     }
+    /// }
+    /// ```
+    pub fn measure_asset_deep<T>(
+        &self,
+        world: &World,
+        handle: impl Into<HandleId>,
+        attribution: HandleAttribution,
+    ) -> Option<DeepMemory>
+    where
+        T: Asset,
+    {
+        let type_id = TypeId::of::<T>();
+        let handle_id = handle.into();
+
+        let default_config = MemoryConfig::default();
+        let memory_config = world
+            .get_resource::<MemoryConfig>()
+            .unwrap_or(&default_config);
+
+        let inner = self.inner.read();
+        let config_generation = inner.config_generation.load(Ordering::Relaxed);
+
+        let entry = inner.datasizes.get(&type_id)?;
+        let shallow = entry.estimate_asset(world, handle_id, memory_config, config_generation)?;
+
+        let handles = match entry.asset_references {
+            Some(_) => {
+                entry.asset_references(world, handle_id, memory_config, config_generation)?
+            }
+            None => Vec::new(),
+        };
+
+        let mut seen = HashSet::default();
+        seen.insert((type_id, handle_id));
+
+        let referenced = inner.referenced_assets(world, memory_config, attribution, handles, seen);
+
+        Some(DeepMemory::new(shallow, referenced))
     }
 
     /// Updates the [`MemoryStats`] for the given type.
@@ -52,13 +445,15 @@ impl MemoryUsage {
         let type_id = TypeId::of::<T>();
 
         let mut inner = self.inner.write();
+        let frame = inner.frame.load(Ordering::Relaxed);
 
         let entry = inner
             .datasizes
             .get_mut(&type_id)
             .expect("Memory usage not tracked for this type. Did you forget to register the type?");
 
-        *entry = MemoryStatsInternal::from(stats);
+        entry.stats = MemoryStatsInternal::from(stats);
+        entry.peaks.get_mut().update(stats, frame);
     }
 
     /// Like [`update_stats`][Self::update_stats] but operates on a shared reference.
@@ -79,11 +474,855 @@ impl MemoryUsage {
             .get(&type_id)
             .expect("Memory usage not tracked for this type. Did you forget to register the type?");
 
-        entry.set(stats);
+        entry.stats.set(stats);
+        entry
+            .peaks
+            .lock()
+            .update(stats, inner.frame.load(Ordering::Relaxed));
+    }
+
+    /// Returns the most recent [`SizeDistribution`] for the given type.
+    ///
+    /// Returns `None` if the type has not been registered, or if no
+    /// distribution has been computed for it yet (see
+    /// [`MemoryConfig::size_distributions`]).
+    ///
+    /// [`MemoryConfig::size_distributions`]: crate::MemoryConfig::size_distributions
+    pub fn get_distribution<T>(&self) -> Option<SizeDistribution>
+    where
+        T: Any,
+    {
+        self.inner
+            .read()
+            .datasizes
+            .get(&TypeId::of::<T>())
+            .and_then(|entry| entry.distribution.lock().clone())
+    }
+
+    /// Updates the [`SizeDistribution`] for the given type.
+    ///
+    /// Like [`update_stats_fast`][Self::update_stats_fast], this operates on a
+    /// shared reference.
+    pub fn update_distribution_fast<T>(&self, distribution: SizeDistribution)
+    where
+        T: Any,
+    {
+        let type_id = TypeId::of::<T>();
+
+        let inner = self.inner.read();
+
+        let entry = inner
+            .datasizes
+            .get(&type_id)
+            .expect("Memory usage not tracked for this type. Did you forget to register the type?");
+
+        *entry.distribution.lock() = Some(distribution);
+    }
+
+    /// Returns the total size of the distinct shared allocations held by the
+    /// instances of the given type.
+    ///
+    /// Returns `None` if the type has not been registered, or if shared
+    /// allocations have not been deduplicated for it yet (see
+    /// [`MemoryConfig::deduplicate_shared_allocations`]).
+    ///
+    /// [`MemoryConfig::deduplicate_shared_allocations`]: crate::MemoryConfig::deduplicate_shared_allocations
+    pub fn get_shared_bytes<T>(&self) -> Option<usize>
+    where
+        T: Any,
+    {
+        self.inner
+            .read()
+            .datasizes
+            .get(&TypeId::of::<T>())
+            .and_then(|entry| *entry.shared_bytes.lock())
+    }
+
+    /// Updates the size of the distinct shared allocations for the given
+    /// type.
+    ///
+    /// Like [`update_stats_fast`][Self::update_stats_fast], this operates on a
+    /// shared reference.
+    pub fn update_shared_bytes_fast<T>(&self, shared_bytes: usize)
+    where
+        T: Any,
+    {
+        let type_id = TypeId::of::<T>();
+
+        let inner = self.inner.read();
+
+        let entry = inner
+            .datasizes
+            .get(&type_id)
+            .expect("Memory usage not tracked for this type. Did you forget to register the type?");
+
+        *entry.shared_bytes.lock() = Some(shared_bytes);
+    }
+
+    /// Returns the largest instances of the given type, largest first.
+    ///
+    /// Returns `None` if the type has not been registered, or if the largest
+    /// instances have not been computed for it yet (see
+    /// [`MemoryConfig::largest_instances`]).
+    ///
+    /// [`MemoryConfig::largest_instances`]: crate::MemoryConfig::largest_instances
+    pub fn get_largest_instances<T>(&self) -> Option<Vec<InstanceStats>>
+    where
+        T: Any,
+    {
+        self.inner
+            .read()
+            .datasizes
+            .get(&TypeId::of::<T>())
+            .and_then(|entry| entry.largest_instances.lock().clone())
+    }
+
+    /// Updates the largest instances of the given type.
+    ///
+    /// Like [`update_stats_fast`][Self::update_stats_fast], this operates on a
+    /// shared reference.
+    pub fn update_largest_instances_fast<T>(&self, instances: Vec<InstanceStats>)
+    where
+        T: Any,
+    {
+        let type_id = TypeId::of::<T>();
+
+        let inner = self.inner.read();
+
+        let entry = inner
+            .datasizes
+            .get(&type_id)
+            .expect("Memory usage not tracked for this type. Did you forget to register the type?");
+
+        *entry.largest_instances.lock() = Some(instances);
+    }
+
+    /// Returns the most recent [`MemoryStats`] of the individual asset with
+    /// the given handle.
+    ///
+    /// Returns `None` if the asset type has not been registered, or if the
+    /// asset has not been measured.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use bevy::prelude::*;
+    /// # use bevy_datasize::prelude::*;
+    /// fn print_texture_sizes(textures: Query<&Handle<Image>>, memory_usage: Res<MemoryUsage>) {
+    ///     for texture in textures.iter() {
+    ///         if let Some(stats) = memory_usage.get_asset_stats::<Image>(texture) {
+    ///             println!("Texture size: {stats}");
+    ///         }
+    ///     }
+    /// }
+    /// ```
+    pub fn get_asset_stats<T>(&self, handle: impl Into<HandleId>) -> Option<MemoryStats>
+    where
+        T: Asset,
+    {
+        let handle_id = handle.into();
+
+        self.inner
+            .read()
+            .datasizes
+            .get(&TypeId::of::<T>())
+            .and_then(|entry| entry.asset_stats.lock().get(&handle_id).copied())
+    }
+
+    /// Replaces the [`MemoryStats`] of the individual assets of the given
+    /// type.
+    ///
+    /// Like [`update_stats_fast`][Self::update_stats_fast], this operates on a
+    /// shared reference.
+    pub fn update_asset_stats_fast<T>(&self, asset_stats: HashMap<HandleId, MemoryStats>)
+    where
+        T: Any,
+    {
+        let type_id = TypeId::of::<T>();
+
+        let inner = self.inner.read();
+
+        let entry = inner
+            .datasizes
+            .get(&type_id)
+            .expect("Memory usage not tracked for this type. Did you forget to register the type?");
+
+        *entry.asset_stats.lock() = asset_stats;
+    }
+
+    /// Updates the [`MemoryStats`] of some of the individual assets of the
+    /// given type in place, removing the assets that are paired with `None`.
+    ///
+    /// Like [`update_stats_fast`][Self::update_stats_fast], this operates on a
+    /// shared reference.
+    pub fn update_changed_asset_stats_fast<T, I>(&self, asset_stats: I)
+    where
+        T: Any,
+        I: IntoIterator<Item = (HandleId, Option<MemoryStats>)>,
+    {
+        let type_id = TypeId::of::<T>();
+
+        let inner = self.inner.read();
+
+        let entry = inner
+            .datasizes
+            .get(&type_id)
+            .expect("Memory usage not tracked for this type. Did you forget to register the type?");
+
+        update_in_place(&mut *entry.asset_stats.lock(), asset_stats);
+    }
+
+    /// Replaces the file paths that the individual assets of the given type
+    /// were loaded from.
+    ///
+    /// Like [`update_stats_fast`][Self::update_stats_fast], this operates on a
+    /// shared reference.
+    pub fn update_asset_paths_fast<T>(&self, asset_paths: HashMap<HandleId, PathBuf>)
+    where
+        T: Any,
+    {
+        let type_id = TypeId::of::<T>();
+
+        let inner = self.inner.read();
+
+        let entry = inner
+            .datasizes
+            .get(&type_id)
+            .expect("Memory usage not tracked for this type. Did you forget to register the type?");
+
+        *entry.asset_paths.lock() = asset_paths;
+    }
+
+    /// Updates the file paths of some of the individual assets of the given
+    /// type in place, removing the assets that are paired with `None`.
+    ///
+    /// Like [`update_stats_fast`][Self::update_stats_fast], this operates on a
+    /// shared reference.
+    pub fn update_changed_asset_paths_fast<T, I>(&self, asset_paths: I)
+    where
+        T: Any,
+        I: IntoIterator<Item = (HandleId, Option<PathBuf>)>,
+    {
+        let type_id = TypeId::of::<T>();
+
+        let inner = self.inner.read();
+
+        let entry = inner
+            .datasizes
+            .get(&type_id)
+            .expect("Memory usage not tracked for this type. Did you forget to register the type?");
+
+        update_in_place(&mut *entry.asset_paths.lock(), asset_paths);
+    }
+
+    /// Returns the estimated memory usage of all tracked assets, grouped by
+    /// the file that they were loaded from and by the folders containing
+    /// those files.
+    ///
+    /// Paths are looked up from the [`AssetServer`] when the asset stats are
+    /// updated. Assets that were not loaded from a file are reported as
+    /// [unattributed](AssetPathReport::unattributed).
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use bevy::prelude::*;
+    /// # use bevy_datasize::prelude::*;
+    /// fn print_terrain_usage(memory_usage: Res<MemoryUsage>) {
+    ///     let report = memory_usage.asset_path_report();
+    ///
+    ///     println!("Terrain textures: {}", report.get_folder("textures/terrain"));
+    /// }
+    /// ```
+    ///
+    /// [`AssetServer`]: bevy::asset::AssetServer
+    pub fn asset_path_report(&self) -> AssetPathReport {
+        let inner = self.inner.read();
+
+        let mut assets = Vec::new();
+
+        for entry in inner.datasizes.values() {
+            let asset_stats = entry.asset_stats.lock();
+            let asset_paths = entry.asset_paths.lock();
+
+            assets.extend(
+                asset_stats
+                    .iter()
+                    .map(|(handle_id, stats)| (asset_paths.get(handle_id).cloned(), *stats)),
+            );
+        }
+
+        AssetPathReport::new(assets)
+    }
+
+    /// Returns the highest [`MemoryStats`] values that have been observed for
+    /// the given type since it was registered or since the last call to
+    /// [`reset_peaks`][Self::reset_peaks].
+    ///
+    /// Returns `None` if the type has not been registered.
+    pub fn get_peaks<T>(&self) -> Option<MemoryPeaks>
+    where
+        T: Any,
+    {
+        self.inner
+            .read()
+            .datasizes
+            .get(&TypeId::of::<T>())
+            .map(|entry| *entry.peaks.lock())
+    }
+
+    /// Resets the peaks of the given type to its current [`MemoryStats`].
+    pub fn reset_peaks<T>(&self)
+    where
+        T: Any,
+    {
+        let inner = self.inner.read();
+
+        if let Some(entry) = inner.datasizes.get(&TypeId::of::<T>()) {
+            entry.reset_peaks(inner.frame.load(Ordering::Relaxed));
+        }
+    }
+
+    /// Resets the peaks of every registered type to its current
+    /// [`MemoryStats`].
+    pub fn reset_all_peaks(&self) {
+        let inner = self.inner.read();
+        let frame = inner.frame.load(Ordering::Relaxed);
+
+        for entry in inner.datasizes.values() {
+            entry.reset_peaks(frame);
+        }
+    }
+
+    /// Returns the number of frames that have begun since the
+    /// [`MemoryUsagePlugin`] was added.
+    ///
+    /// [`MemoryUsagePlugin`]: crate::MemoryUsagePlugin
+    pub fn frame(&self) -> u64 {
+        self.inner.read().frame.load(Ordering::Relaxed)
+    }
+
+    /// Returns `true` if stats are being updated during the current frame.
+    ///
+    /// This is determined at the start of each frame according to
+    /// [`MemoryConfig::sampling`].
+    ///
+    /// [`MemoryConfig::sampling`]: crate::MemoryConfig::sampling
+    pub fn is_sampling(&self) -> bool {
+        self.inner.read().is_sampling.load(Ordering::Relaxed)
+    }
+
+    /// Requests that stats be updated during the next frame, regardless of
+    /// [`MemoryConfig::sampling`].
+    ///
+    /// [`MemoryConfig::sampling`]: crate::MemoryConfig::sampling
+    pub fn request_sample(&self) {
+        self.inner
+            .read()
+            .is_sample_requested
+            .store(true, Ordering::Relaxed);
+    }
+
+    /// Clears a pending [`request_sample`][Self::request_sample], returning
+    /// `true` if there was one.
+    pub(crate) fn take_sample_request(&self) -> bool {
+        self.inner
+            .read()
+            .is_sample_requested
+            .swap(false, Ordering::Relaxed)
+    }
+
+    /// Appends the current stats of every registered type to its history,
+    /// keeping at most `history_length` samples per type.
+    pub(crate) fn record_history(&self, history_length: usize) {
+        let mut inner = self.inner.write();
+
+        let frame = inner.frame.load(Ordering::Relaxed);
+        let elapsed = inner.start.elapsed();
+
+        for entry in inner.datasizes.values_mut() {
+            while entry.history.len() >= history_length.max(1) {
+                entry.history.pop_front();
+            }
+
+            entry.history.push_back(MemorySample {
+                frame,
+                elapsed,
+                stats: entry.stats.get(),
+            });
+        }
+    }
+
+    /// Records the start of a new frame.
+    pub(crate) fn begin_frame(&self, frame: u64, is_sampling: bool) {
+        let inner = self.inner.read();
+
+        inner.frame.store(frame, Ordering::Relaxed);
+        inner.is_sampling.store(is_sampling, Ordering::Relaxed);
+    }
+
+    /// Returns a number that changes whenever the [`MemoryConfig`] changes.
+    ///
+    /// This is used to decide when estimators have to be recreated.
+    pub(crate) fn config_generation(&self) -> u64 {
+        self.inner.read().config_generation.load(Ordering::Relaxed)
+    }
+
+    /// Records that the [`MemoryConfig`] changed.
+    pub(crate) fn config_changed(&self) {
+        self.inner
+            .read()
+            .config_generation
+            .fetch_add(1, Ordering::Relaxed);
     }
 }
 
-#[derive(Debug, Default)]
+#[derive(Debug)]
 struct MemoryUsageInner {
-    datasizes: HashMap<TypeId, MemoryStatsInternal>,
+    datasizes: HashMap<TypeId, TypeEntry>,
+    frame: AtomicU64,
+    is_sampling: AtomicBool,
+    is_sample_requested: AtomicBool,
+    config_generation: AtomicU64,
+    start: Instant,
+}
+
+/// Inserts the given values into `map`, or removes them if they are `None`.
+fn update_in_place<V, I>(map: &mut HashMap<HandleId, V>, values: I)
+where
+    I: IntoIterator<Item = (HandleId, Option<V>)>,
+{
+    for (handle_id, value) in values {
+        match value {
+            Some(value) => {
+                map.insert(handle_id, value);
+            }
+            None => {
+                map.remove(&handle_id);
+            }
+        }
+    }
+}
+
+impl MemoryUsageInner {
+    /// Returns the info and stats of every registered type, ordered by their
+    /// [`TypeKind`] and then by their short name.
+    fn all_stats(&self) -> Vec<(TypeInfo, MemoryStats)> {
+        let mut all_stats: Vec<_> = self
+            .datasizes
+            .values()
+            .map(|entry| (entry.info.clone(), entry.stats.get()))
+            .collect();
+
+        all_stats.sort_by(|(a, _), (b, _)| {
+            (a.kind, &a.short_name, a.type_name).cmp(&(b.kind, &b.short_name, b.type_name))
+        });
+
+        all_stats
+    }
+}
+
+impl MemoryUsageInner {
+    /// Returns the registered assets that are referenced by the registered
+    /// components and [`Handle`] components of the given entity.
+    ///
+    /// [`Handle`]: bevy::asset::Handle
+    fn handles_of_entity(
+        &self,
+        world: &World,
+        entity: Entity,
+        memory_config: &MemoryConfig,
+    ) -> HashSet<(TypeId, HandleId)> {
+        let config_generation = self.config_generation.load(Ordering::Relaxed);
+        let mut handles = HashSet::default();
+
+        for (type_id, entry) in self.datasizes.iter() {
+            if let Some(handle_id) = entry
+                .asset_estimator
+                .and_then(|asset| (asset.handle_of)(world, entity))
+            {
+                handles.insert((*type_id, handle_id));
+            }
+
+            if let Some(references) =
+                entry.component_references(world, entity, memory_config, config_generation)
+            {
+                handles.extend(references);
+            }
+        }
+
+        handles
+    }
+
+    /// Counts the entities and assets that reference each asset.
+    fn count_referrers(
+        &self,
+        world: &World,
+        memory_config: &MemoryConfig,
+    ) -> HashMap<(TypeId, HandleId), usize> {
+        let config_generation = self.config_generation.load(Ordering::Relaxed);
+        let mut referrers: HashMap<(TypeId, HandleId), usize> = HashMap::default();
+
+        for archetype in world.archetypes().iter() {
+            for entity in archetype.entities() {
+                for handle in self.handles_of_entity(world, *entity, memory_config) {
+                    *referrers.entry(handle).or_default() += 1;
+                }
+            }
+        }
+
+        for entry in self.datasizes.values() {
+            for handles in entry.all_asset_references(world, memory_config, config_generation) {
+                let handles: HashSet<_> = handles.into_iter().collect();
+
+                for handle in handles {
+                    *referrers.entry(handle).or_default() += 1;
+                }
+            }
+        }
+
+        referrers
+    }
+
+    /// Follows the given handles, and the handles held by the assets they
+    /// point to, and sums the [`MemoryStats`] attributed to the referrer for
+    /// each asset type.
+    ///
+    /// Assets in `seen` are skipped.
+    fn referenced_assets<I>(
+        &self,
+        world: &World,
+        memory_config: &MemoryConfig,
+        attribution: HandleAttribution,
+        handles: I,
+        mut seen: HashSet<(TypeId, HandleId)>,
+    ) -> Vec<(TypeInfo, MemoryStats)>
+    where
+        I: IntoIterator<Item = (TypeId, HandleId)>,
+    {
+        let referrers = match attribution {
+            HandleAttribution::Full => None,
+            HandleAttribution::Proportional => Some(self.count_referrers(world, memory_config)),
+        };
+
+        let config_generation = self.config_generation.load(Ordering::Relaxed);
+        let mut totals: HashMap<TypeId, SharedStats> = HashMap::default();
+
+        // Each handle is paired with the share of the value holding it that is
+        // attributed to the referrer, and with the assets on the path to it.
+        let mut stack: Vec<_> = unique(handles)
+            .map(|handle| (handle, 1.0, Vec::new()))
+            .collect();
+
+        while let Some((handle, share, path)) = stack.pop() {
+            let (type_id, handle_id) = handle;
+
+            let share = match &referrers {
+                // The referrer keeps all of every asset alive, so each asset
+                // is only counted once.
+                None => {
+                    if !seen.insert(handle) {
+                        continue;
+                    }
+
+                    share
+                }
+
+                // Every path to an asset adds to the share of it that the
+                // referrer holds, so only cycles are skipped.
+                Some(referrers) => {
+                    if seen.contains(&handle) || path.contains(&handle) {
+                        continue;
+                    }
+
+                    share / referrers.get(&handle).copied().unwrap_or(1).max(1) as f64
+                }
+            };
+
+            let entry = match self.datasizes.get(&type_id) {
+                Some(entry) => entry,
+                None => continue,
+            };
+
+            if let Some(stats) =
+                entry.estimate_asset(world, handle_id, memory_config, config_generation)
+            {
+                totals.entry(type_id).or_default().add_share(stats, share);
+            }
+
+            if let Some(handles) =
+                entry.asset_references(world, handle_id, memory_config, config_generation)
+            {
+                let mut path = path;
+                path.push(handle);
+
+                stack.extend(unique(handles).map(|handle| (handle, share, path.clone())));
+            }
+        }
+
+        totals
+            .into_iter()
+            .map(|(type_id, stats)| (self.datasizes[&type_id].info.clone(), stats.rounded()))
+            .collect()
+    }
+}
+
+/// Removes duplicate handles, since a value that holds several handles to the
+/// same asset only counts as a single referrer of it.
+fn unique<I>(handles: I) -> impl Iterator<Item = (TypeId, HandleId)>
+where
+    I: IntoIterator<Item = (TypeId, HandleId)>,
+{
+    handles.into_iter().collect::<HashSet<_>>().into_iter()
+}
+
+impl Default for MemoryUsageInner {
+    fn default() -> Self {
+        Self {
+            datasizes: Default::default(),
+            frame: Default::default(),
+            is_sampling: AtomicBool::new(true),
+            is_sample_requested: Default::default(),
+            config_generation: Default::default(),
+            start: Instant::now(),
+        }
+    }
+}
+
+#[derive(Debug)]
+struct TypeEntry {
+    info: TypeInfo,
+    stats: MemoryStatsInternal,
+    history: VecDeque<MemorySample>,
+    peaks: Mutex<MemoryPeaks>,
+    distribution: Mutex<Option<SizeDistribution>>,
+    largest_instances: Mutex<Option<Vec<InstanceStats>>>,
+    shared_bytes: Mutex<Option<usize>>,
+    asset_stats: Mutex<HashMap<HandleId, MemoryStats>>,
+    asset_paths: Mutex<HashMap<HandleId, PathBuf>>,
+    component_estimator: Option<ComponentEstimatorFn>,
+    component_estimator_state: Mutex<EstimatorState>,
+    asset_estimator: Option<AssetEstimatorFns>,
+    asset_estimator_state: Mutex<EstimatorState>,
+    component_references: Option<ComponentReferencesFn>,
+    component_references_state: Mutex<EstimatorState>,
+    asset_references: Option<AssetReferencesFns>,
+    asset_references_state: Mutex<EstimatorState>,
+}
+
+impl TypeEntry {
+    fn new(info: TypeInfo) -> Self {
+        Self {
+            info,
+            stats: Default::default(),
+            history: Default::default(),
+            peaks: Default::default(),
+            distribution: Default::default(),
+            largest_instances: Default::default(),
+            shared_bytes: Default::default(),
+            asset_stats: Default::default(),
+            asset_paths: Default::default(),
+            component_estimator: None,
+            component_estimator_state: Default::default(),
+            asset_estimator: None,
+            asset_estimator_state: Default::default(),
+            component_references: None,
+            component_references_state: Default::default(),
+            asset_references: None,
+            asset_references_state: Default::default(),
+        }
+    }
+
+    /// Estimates the [`MemoryStats`] of the component of this type on the
+    /// given entity, if it has one and there is an estimator for it.
+    fn estimate_component(
+        &self,
+        world: &World,
+        entity: Entity,
+        memory_config: &MemoryConfig,
+        config_generation: u64,
+    ) -> Option<MemoryStats> {
+        let estimate = self.component_estimator?;
+
+        estimate(
+            world,
+            entity,
+            memory_config,
+            config_generation,
+            &mut *self.component_estimator_state.lock(),
+        )
+    }
+
+    /// Estimates the [`MemoryStats`] of the asset of this type with the given
+    /// [`HandleId`], if it exists and there is an estimator for it.
+    fn estimate_asset(
+        &self,
+        world: &World,
+        handle_id: HandleId,
+        memory_config: &MemoryConfig,
+        config_generation: u64,
+    ) -> Option<MemoryStats> {
+        let estimate = self.asset_estimator?.estimate;
+
+        estimate(
+            world,
+            handle_id,
+            memory_config,
+            config_generation,
+            &mut *self.asset_estimator_state.lock(),
+        )
+    }
+
+    /// Returns the handles held by the component of this type on the given
+    /// entity, if it has one and there is a [`HandleReferences`] for it.
+    fn component_references(
+        &self,
+        world: &World,
+        entity: Entity,
+        memory_config: &MemoryConfig,
+        config_generation: u64,
+    ) -> Option<Vec<(TypeId, HandleId)>> {
+        let visit = self.component_references?;
+
+        visit(
+            world,
+            entity,
+            memory_config,
+            config_generation,
+            &mut *self.component_references_state.lock(),
+        )
+    }
+
+    /// Returns the handles held by the asset of this type with the given
+    /// [`HandleId`], if it exists and there is a [`HandleReferences`] for it.
+    fn asset_references(
+        &self,
+        world: &World,
+        handle_id: HandleId,
+        memory_config: &MemoryConfig,
+        config_generation: u64,
+    ) -> Option<Vec<(TypeId, HandleId)>> {
+        let visit = self.asset_references?.visit;
+
+        visit(
+            world,
+            handle_id,
+            memory_config,
+            config_generation,
+            &mut *self.asset_references_state.lock(),
+        )
+    }
+
+    /// Returns the handles held by each asset of this type, if there is a
+    /// [`HandleReferences`] for it.
+    fn all_asset_references(
+        &self,
+        world: &World,
+        memory_config: &MemoryConfig,
+        config_generation: u64,
+    ) -> Vec<Vec<(TypeId, HandleId)>> {
+        let visit_all = match self.asset_references {
+            Some(references) => references.visit_all,
+            None => return Vec::new(),
+        };
+
+        visit_all(
+            world,
+            memory_config,
+            config_generation,
+            &mut *self.asset_references_state.lock(),
+        )
+    }
+
+    fn reset_peaks(&self, frame: u64) {
+        *self.peaks.lock() = MemoryPeaks::from_stats(self.stats.get(), frame);
+    }
+}
+
+/***************************************************************************************************
+
+                             dMMMMMMP dMMMMMP .dMMMb dMMMMMMP .dMMMb
+                               dMP   dMP     dMP" VP   dMP   dMP" VP
+                              dMP   dMMMP    VMMMb    dMP    VMMMb
+                             dMP   dMP     dP .dMP   dMP   dP .dMP
+                            dMP   dMMMMMP  VMMMP"   dMP    VMMMP"
+
+***************************************************************************************************/
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use bevy::reflect::TypeUuid;
+
+    #[derive(TypeUuid)]
+    #[uuid = "8a7c6e2d-4b1f-4c3a-9e5d-2f0b1a6c7d01"]
+    struct Texture;
+
+    #[derive(TypeUuid)]
+    #[uuid = "8a7c6e2d-4b1f-4c3a-9e5d-2f0b1a6c7d02"]
+    struct Sound;
+
+    fn stats(total_heap_bytes: usize) -> MemoryStats {
+        MemoryStats {
+            count: 1,
+            total_stack_bytes: 0,
+            total_heap_bytes,
+            total_device_bytes: 0,
+        }
+    }
+
+    #[test]
+    fn updates_asset_stats_in_place() {
+        let mut memory_usage = MemoryUsage::default();
+        memory_usage.register_type_with_kind::<Texture>(TypeKind::Asset);
+
+        let [a, b, c] = [(); 3].map(|_| HandleId::random::<Texture>());
+
+        memory_usage.update_asset_stats_fast::<Texture>(
+            [(a, stats(100)), (b, stats(200))].into_iter().collect(),
+        );
+        assert_eq!(memory_usage.get_asset_stats::<Texture>(a), Some(stats(100)));
+        assert_eq!(memory_usage.get_asset_stats::<Texture>(c), None);
+
+        // Entries paired with `None` are removed, and the others are left
+        // alone.
+        memory_usage
+            .update_changed_asset_stats_fast::<Texture, _>([(a, None), (c, Some(stats(300)))]);
+        assert_eq!(memory_usage.get_asset_stats::<Texture>(a), None);
+        assert_eq!(memory_usage.get_asset_stats::<Texture>(b), Some(stats(200)));
+        assert_eq!(memory_usage.get_asset_stats::<Texture>(c), Some(stats(300)));
+
+        // Replacing the stats drops everything that is not given.
+        memory_usage.update_asset_stats_fast::<Texture>([(c, stats(400))].into_iter().collect());
+        assert_eq!(memory_usage.get_asset_stats::<Texture>(b), None);
+        assert_eq!(memory_usage.get_asset_stats::<Texture>(c), Some(stats(400)));
+
+        // Unregistered types have no stats.
+        assert_eq!(memory_usage.get_asset_stats::<Sound>(c), None);
+    }
+
+    #[test]
+    fn updates_asset_paths_in_place() {
+        let mut memory_usage = MemoryUsage::default();
+        memory_usage.register_type_with_kind::<Texture>(TypeKind::Asset);
+
+        let [a, b] = [(); 2].map(|_| HandleId::random::<Texture>());
+
+        memory_usage.update_asset_stats_fast::<Texture>(
+            [(a, stats(100)), (b, stats(200))].into_iter().collect(),
+        );
+        memory_usage.update_asset_paths_fast::<Texture>(
+            [(a, PathBuf::from("textures/a.png"))].into_iter().collect(),
+        );
+        memory_usage.update_changed_asset_paths_fast::<Texture, _>([
+            (a, None),
+            (b, Some(PathBuf::from("textures/b.png"))),
+        ]);
+
+        let report = memory_usage.asset_path_report();
+        assert_eq!(report.get_file("textures/a.png"), None);
+        assert_eq!(report.get_file("textures/b.png"), Some(stats(200)));
+        assert_eq!(report.unattributed(), stats(100));
+    }
 }
@@ -0,0 +1,188 @@
+use std::sync::Arc;
+
+use bevy::utils::HashMap;
+
+use crate::{estimator::ForwardingEstimator, DataSize, DataSizeEstimator};
+
+/// Tracks the shared allocations, such as the payloads of [`Arc`]s, that have
+/// been seen during a measurement pass so that each one is only counted once.
+///
+/// Shared allocations are reported by
+/// [`DataSizeEstimator::visit_shared_allocations`].
+///
+/// # Example
+///
+/// ```
+/// # use std::sync::Arc;
+/// # use bevy_datasize::{estimator::ArcEstimator, MemoryStats, SharedAllocations};
+/// let data = Arc::new(vec![0u8; 100]);
+/// let values = vec![data.clone(), data.clone(), data];
+///
+/// let naive = MemoryStats::from_values_with_estimator(values.iter(), &ArcEstimator);
+/// assert_eq!(naive.total_heap_bytes, 3 * 124);
+///
+/// let mut shared = SharedAllocations::default();
+/// let stats = MemoryStats::from_values_with_shared_allocations(
+///     values.iter(),
+///     &ArcEstimator,
+///     &mut shared,
+/// );
+/// assert_eq!(stats.total_heap_bytes, 124);
+/// assert_eq!(shared.shared_bytes(), 124);
+/// ```
+#[derive(Debug, Default)]
+pub struct SharedAllocations {
+    // Maps the address of each allocation to its size.
+    seen: HashMap<usize, usize>,
+    shared_bytes: usize,
+    duplicate_bytes: usize,
+}
+
+impl SharedAllocations {
+    /// Records the payload of the given [`Arc`].
+    #[inline]
+    pub fn visit_arc<T>(&mut self, arc: &Arc<T>)
+    where
+        T: DataSize,
+    {
+        self.visit_arc_with_estimator(arc, &ForwardingEstimator);
+    }
+
+    /// Records the payload of the given [`Arc`], using a specific
+    /// [`DataSizeEstimator`] to estimate its heap size.
+    #[inline]
+    pub fn visit_arc_with_estimator<T, E>(&mut self, arc: &Arc<T>, estimator: &E)
+    where
+        E: DataSizeEstimator<T>,
+    {
+        let size = std::mem::size_of::<T>() + estimator.estimate_heap_size(&**arc);
+
+        self.visit(Arc::as_ptr(arc) as *const () as usize, size);
+    }
+
+    /// Records a shared allocation of `size` bytes at the given address.
+    ///
+    /// The size must already be included in the heap size estimated for the
+    /// value that holds the allocation.
+    pub fn visit(&mut self, address: usize, size: usize) {
+        if self.seen.insert(address, size).is_none() {
+            self.shared_bytes += size;
+        } else {
+            self.duplicate_bytes += size;
+        }
+    }
+
+    /// Returns the total size of the distinct shared allocations seen so far.
+    ///
+    /// Each of these bytes is counted once in the heap size of the measured
+    /// values.
+    #[inline]
+    pub fn shared_bytes(&self) -> usize {
+        self.shared_bytes
+    }
+
+    /// Returns the number of distinct shared allocations seen so far.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.seen.len()
+    }
+
+    /// Returns `true` if no shared allocations have been seen.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.seen.is_empty()
+    }
+
+    /// Returns the size of the allocations that were seen again since the
+    /// last call, and resets it.
+    pub(crate) fn take_duplicate_bytes(&mut self) -> usize {
+        std::mem::take(&mut self.duplicate_bytes)
+    }
+
+    /// Returns the address and size of each distinct shared allocation seen
+    /// so far.
+    pub(crate) fn iter(&self) -> impl Iterator<Item = (usize, usize)> + '_ {
+        self.seen.iter().map(|(address, size)| (*address, *size))
+    }
+}
+
+/// Reports the shared allocations, such as the payloads of [`Arc`]s, that are
+/// included in the [`DataSize`] heap size of a value.
+///
+/// The [`DataSize`] derive has no way to report these, so they are counted
+/// once per value that holds them. Implement this trait next to [`DataSize`],
+/// and register the type with [`SharedForwardingEstimator`] to count each
+/// shared allocation only once when
+/// [`MemoryConfig::deduplicate_shared_allocations`] is enabled.
+///
+/// # Example
+///
+/// ```
+/// # use std::sync::Arc;
+/// # use bevy::prelude::*;
+/// # use bevy_datasize::{app_ext::RegisterTypesWithEstimator, prelude::*};
+/// # use bevy_datasize::{estimator::SharedForwardingEstimator, SharedAllocations, VisitSharedAllocations};
+/// #[derive(Component)]
+/// struct Terrain {
+///     heights: Arc<Vec<f32>>,
+/// }
+///
+/// impl DataSize for Terrain {
+///     const IS_DYNAMIC: bool = true;
+///     const STATIC_HEAP_SIZE: usize = 0;
+///
+///     fn estimate_heap_size(&self) -> usize {
+///         std::mem::size_of::<Vec<f32>>() + self.heights.estimate_heap_size()
+///     }
+/// }
+///
+/// impl VisitSharedAllocations for Terrain {
+///     fn visit_shared_allocations(&self, shared: &mut SharedAllocations) {
+///         self.heights.visit_shared_allocations(shared);
+///     }
+/// }
+///
+/// App::new()
+///     .add_plugins(MinimalPlugins)
+///     .add_plugin(MemoryUsagePlugin)
+///     .register_component_with_estimator::<Terrain, SharedForwardingEstimator>();
+/// ```
+///
+/// [`SharedForwardingEstimator`]: crate::estimator::SharedForwardingEstimator
+/// [`MemoryConfig::deduplicate_shared_allocations`]: crate::MemoryConfig::deduplicate_shared_allocations
+pub trait VisitSharedAllocations {
+    /// Records each shared allocation held by `self` in `shared`.
+    fn visit_shared_allocations(&self, shared: &mut SharedAllocations);
+}
+
+impl<T: DataSize> VisitSharedAllocations for Arc<T> {
+    #[inline]
+    fn visit_shared_allocations(&self, shared: &mut SharedAllocations) {
+        shared.visit_arc(self);
+    }
+}
+
+impl<T: VisitSharedAllocations> VisitSharedAllocations for Option<T> {
+    #[inline]
+    fn visit_shared_allocations(&self, shared: &mut SharedAllocations) {
+        if let Some(value) = self {
+            value.visit_shared_allocations(shared);
+        }
+    }
+}
+
+impl<T: VisitSharedAllocations> VisitSharedAllocations for Box<T> {
+    #[inline]
+    fn visit_shared_allocations(&self, shared: &mut SharedAllocations) {
+        (**self).visit_shared_allocations(shared);
+    }
+}
+
+impl<T: VisitSharedAllocations> VisitSharedAllocations for Vec<T> {
+    #[inline]
+    fn visit_shared_allocations(&self, shared: &mut SharedAllocations) {
+        for value in self.iter() {
+            value.visit_shared_allocations(shared);
+        }
+    }
+}
@@ -1,13 +1,14 @@
 use std::{
     any::Any,
     fmt,
-    ops::{Add, Mul},
+    iter::Sum,
+    ops::{Add, Mul, Sub},
     sync::atomic::{AtomicUsize, Ordering},
 };
 
 use bytesize::ByteSize;
 
-use crate::{estimator::ForwardingEstimator, DataSize, DataSizeEstimator};
+use crate::{estimator::ForwardingEstimator, DataSize, DataSizeEstimator, SharedAllocations};
 
 /// Memory usage statistics for a single data type.
 ///
@@ -32,6 +33,7 @@ use crate::{estimator::ForwardingEstimator, DataSize, DataSizeEstimator};
 /// assert_eq!(stats.count, 2);
 /// assert_eq!(stats.total_stack_bytes, 48);
 /// assert_eq!(stats.total_heap_bytes, 200);
+/// assert_eq!(stats.total_device_bytes, 0);
 /// assert_eq!(stats.total_bytes(), 248);
 /// assert_eq!(format!("{stats}"), "2 (248 B)")
 /// ```
@@ -53,16 +55,40 @@ pub struct MemoryStats {
     ///
     /// [`heap_size_of`]: Self::heap_size_of
     pub total_heap_bytes: usize,
+
+    /// The estimated total number of bytes of device (GPU) memory used by
+    /// instances of this data type.
+    ///
+    /// Device memory is kept apart from host memory, and is not included in
+    /// [`total_bytes`]. See [`device_size_of`] for details on the meaning of
+    /// this quantity.
+    ///
+    /// [`total_bytes`]: Self::total_bytes
+    /// [`device_size_of`]: Self::device_size_of
+    pub total_device_bytes: usize,
 }
 
 impl MemoryStats {
     /// Returns the sum of `total_stack_bytes` and `total_heap_bytes` for
     /// `self`.
+    ///
+    /// This is the host memory used by the instances, and does not include
+    /// `total_device_bytes`.
     #[inline]
     pub fn total_bytes(&self) -> usize {
         self.total_stack_bytes + self.total_heap_bytes
     }
 
+    /// Returns the sum of `total_bytes()` and `total_device_bytes` for
+    /// `self`.
+    ///
+    /// This is what rankings, such as the largest instances or the ordering
+    /// of types in a report, are based on.
+    #[inline]
+    pub fn total_including_device(&self) -> usize {
+        self.total_bytes() + self.total_device_bytes
+    }
+
     /// Returns the computed memory statistics for a single value.
     #[inline]
     pub fn from_value<T>(value: &T) -> Self
@@ -85,6 +111,7 @@ impl MemoryStats {
                 count: 1,
                 total_stack_bytes: Self::stack_size_of(value),
                 total_heap_bytes: Self::heap_size_of_with_estimator(value, estimator),
+                total_device_bytes: Self::device_size_of_with_estimator(value, estimator),
             }
         } else {
             Self::from_noheap_type::<T>()
@@ -122,12 +149,65 @@ impl MemoryStats {
         }
     }
 
+    /// Returns the computed memory statistics for a single value, counting
+    /// the shared allocations that were already recorded in `shared` as part
+    /// of another value.
+    ///
+    /// See [`SharedAllocations`] for details.
+    #[inline]
+    pub fn from_value_with_shared_allocations<T, E>(
+        value: &T,
+        estimator: &E,
+        shared: &mut SharedAllocations,
+    ) -> Self
+    where
+        T: Any,
+        E: DataSizeEstimator<T>,
+    {
+        let mut stats = Self::from_value_with_estimator(value, estimator);
+
+        if <E as DataSizeEstimator<T>>::IS_DYNAMIC {
+            estimator.visit_shared_allocations(value, shared);
+
+            stats.total_heap_bytes = stats
+                .total_heap_bytes
+                .saturating_sub(shared.take_duplicate_bytes());
+        }
+
+        stats
+    }
+
+    /// Returns the computed memory statistics for a collection of values,
+    /// counting each shared allocation only once.
+    ///
+    /// The size of the distinct shared allocations is available from
+    /// [`SharedAllocations::shared_bytes`] afterwards. The same
+    /// [`SharedAllocations`] can be passed to several calls to deduplicate
+    /// across all of them.
+    #[inline]
+    pub fn from_values_with_shared_allocations<'a, T, E, I>(
+        values: I,
+        estimator: &E,
+        shared: &mut SharedAllocations,
+    ) -> Self
+    where
+        T: Any,
+        E: DataSizeEstimator<T>,
+        I: IntoIterator<Item = &'a T>,
+    {
+        values
+            .into_iter()
+            .map(|value| Self::from_value_with_shared_allocations(value, estimator, shared))
+            .sum()
+    }
+
     #[inline]
     fn from_noheap_type<T>() -> Self {
         Self {
             count: 1,
             total_stack_bytes: std::mem::size_of::<T>(),
             total_heap_bytes: 0,
+            total_device_bytes: 0,
         }
     }
 
@@ -169,6 +249,21 @@ impl MemoryStats {
         estimator.estimate_heap_size(value)
     }
 
+    /// Returns the estimated device memory size of the given value using a
+    /// specific [`DataSizeEstimator`].
+    ///
+    /// This quantity represents how many bytes of device (GPU) memory, such as
+    /// vertex buffers and textures, are owned by the value.
+    ///
+    /// This quantity is **estimated** and may not be 100% accurate for all types.
+    #[inline]
+    pub fn device_size_of_with_estimator<T, E>(value: &T, estimator: &E) -> usize
+    where
+        E: DataSizeEstimator<T>,
+    {
+        estimator.estimate_device_size(value)
+    }
+
     /// Returns the estimated total size of the given value.
     ///
     /// This quantity is the sum of [`stack_size_of`] and [`heap_size_of`].
@@ -203,6 +298,31 @@ impl Add for MemoryStats {
             count: self.count + rhs.count,
             total_stack_bytes: self.total_stack_bytes + rhs.total_stack_bytes,
             total_heap_bytes: self.total_heap_bytes + rhs.total_heap_bytes,
+            total_device_bytes: self.total_device_bytes + rhs.total_device_bytes,
+        }
+    }
+}
+
+impl Sum for MemoryStats {
+    #[inline]
+    fn sum<I>(iter: I) -> Self
+    where
+        I: Iterator<Item = Self>,
+    {
+        iter.fold(Self::default(), Add::add)
+    }
+}
+
+impl Sub for MemoryStats {
+    type Output = MemoryStats;
+
+    #[inline]
+    fn sub(self, rhs: Self) -> Self::Output {
+        Self {
+            count: self.count - rhs.count,
+            total_stack_bytes: self.total_stack_bytes - rhs.total_stack_bytes,
+            total_heap_bytes: self.total_heap_bytes - rhs.total_heap_bytes,
+            total_device_bytes: self.total_device_bytes - rhs.total_device_bytes,
         }
     }
 }
@@ -216,6 +336,7 @@ impl Mul<usize> for MemoryStats {
             count: self.count * rhs,
             total_stack_bytes: self.total_stack_bytes * rhs,
             total_heap_bytes: self.total_heap_bytes * rhs,
+            total_device_bytes: self.total_device_bytes * rhs,
         }
     }
 }
@@ -227,7 +348,13 @@ impl fmt::Display for MemoryStats {
         // let total_heap_bytes = ByteSize(self.total_heap_bytes as u64);
         let total_bytes = ByteSize(self.total_bytes() as u64);
 
-        write!(f, "{count} ({total_bytes})")
+        if self.total_device_bytes == 0 {
+            write!(f, "{count} ({total_bytes})")
+        } else {
+            let total_device_bytes = ByteSize(self.total_device_bytes as u64);
+
+            write!(f, "{count} ({total_bytes} + {total_device_bytes} device)")
+        }
     }
 }
 
@@ -236,6 +363,7 @@ pub(crate) struct MemoryStatsInternal {
     count: AtomicUsize,
     total_stack_bytes: AtomicUsize,
     total_heap_bytes: AtomicUsize,
+    total_device_bytes: AtomicUsize,
 }
 
 impl MemoryStatsInternal {
@@ -244,6 +372,7 @@ impl MemoryStatsInternal {
             count: self.count.load(Ordering::Relaxed),
             total_stack_bytes: self.total_stack_bytes.load(Ordering::Relaxed),
             total_heap_bytes: self.total_heap_bytes.load(Ordering::Relaxed),
+            total_device_bytes: self.total_device_bytes.load(Ordering::Relaxed),
         }
     }
 
@@ -253,6 +382,8 @@ impl MemoryStatsInternal {
             .store(stats.total_stack_bytes, Ordering::Relaxed);
         self.total_heap_bytes
             .store(stats.total_heap_bytes, Ordering::Relaxed);
+        self.total_device_bytes
+            .store(stats.total_device_bytes, Ordering::Relaxed);
     }
 }
 
@@ -262,6 +393,7 @@ impl From<MemoryStats> for MemoryStatsInternal {
             count: AtomicUsize::new(stats.count),
             total_stack_bytes: AtomicUsize::new(stats.total_stack_bytes),
             total_heap_bytes: AtomicUsize::new(stats.total_heap_bytes),
+            total_device_bytes: AtomicUsize::new(stats.total_device_bytes),
         }
     }
 }
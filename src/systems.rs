@@ -1,69 +1,361 @@
 //! Systems used by this library.
 
-use std::any::Any;
+use std::{any::Any, hash::Hash, path::PathBuf, time::Instant};
 
 use bevy::{
-    asset::{Asset, Assets},
+    asset::{Asset, AssetEvent, AssetServer, Assets, HandleId},
     ecs::{
         component::Component,
-        system::{Query, Res, Resource},
+        entity::Entity,
+        event::EventReader,
+        query::Changed,
+        system::{Local, Query, RemovedComponents, Res, Resource},
     },
+    utils::{HashMap, HashSet},
 };
+use parking_lot::Mutex;
 
 #[cfg(feature = "bevy_render")]
-use bevy::render::render_asset::{RenderAsset, RenderAssets};
+use bevy::render::render_asset::{ExtractedAssets, RenderAsset, RenderAssets};
 
-use crate::{estimator::FromConfig, DataSizeEstimator, MemoryConfig, MemoryStats, MemoryUsage};
+use crate::{
+    estimator::FromConfig, instance::largest_instances, ComponentTrackingMode, DataSizeEstimator,
+    InstanceId, InstanceStats, MemoryConfig, MemoryStats, MemoryUsage, SamplingPolicy,
+    SharedAllocations, SizeDistribution,
+};
 
-// TODO: change detection!
 // TODO: tracing scopes!
 
+/// This system advances the frame counter of the [`MemoryUsage`] resource and
+/// decides whether stats will be updated during this frame, according to
+/// [`MemoryConfig::sampling`].
+///
+/// If stats were updated during the previous frame, they are also appended to
+/// the history of each type (see [`MemoryConfig::history_length`]).
+///
+/// This should run before any of the systems that update stats.
+pub fn begin_frame(
+    mut last_sample: Local<Option<(u64, Instant)>>,
+    memory_config: Res<MemoryConfig>,
+    memory_usage: Res<MemoryUsage>,
+) {
+    // Estimators check this on every run, so they are recreated even if the
+    // config changed while their type was not being updated.
+    if memory_config.is_changed() {
+        memory_usage.config_changed();
+    }
+
+    // Stats from the previous frame are complete by now, including the ones
+    // that were updated in the render world.
+    if memory_config.history_length > 0 && memory_usage.frame() > 0 && memory_usage.is_sampling() {
+        memory_usage.record_history(memory_config.history_length);
+    }
+
+    let frame = memory_usage.frame() + 1;
+    let now = Instant::now();
+
+    let is_requested = memory_usage.take_sample_request();
+
+    let is_due = match memory_config.sampling {
+        SamplingPolicy::EveryFrame => true,
+        SamplingPolicy::EveryNFrames(frames) => match *last_sample {
+            Some((last_frame, _)) => frame - last_frame >= u64::from(frames.max(1)),
+            None => true,
+        },
+        SamplingPolicy::Interval(interval) => match *last_sample {
+            Some((_, last_time)) => now.duration_since(last_time) >= interval,
+            None => true,
+        },
+        SamplingPolicy::OnDemand => false,
+    };
+
+    let is_sampling = is_requested || is_due;
+
+    if is_sampling {
+        *last_sample = Some((frame, now));
+    }
+
+    memory_usage.begin_frame(frame, is_sampling);
+}
+
 /// This system updates the [`MemoryStats`] for the given component type `T`
-/// using the given [`DataSizeEstimator`] type.
+/// using the given [`DataSizeEstimator`] type, by re-estimating every instance
+/// of `T`.
+///
+/// This only does anything if [`MemoryConfig::component_tracking`] is
+/// [`ComponentTrackingMode::Full`]. See
+/// [`update_stats_for_changed_components`] for the incremental mode.
 pub fn update_stats_for_component<T, E>(
-    query: Query<&T>,
+    components: Query<&T>,
+    mut estimator: Local<PersistentEstimator<E>>,
     memory_config: Res<MemoryConfig>,
     memory_usage: Res<MemoryUsage>,
 ) where
     T: Any + Component,
-    E: DataSizeEstimator<T> + FromConfig,
+    E: DataSizeEstimator<T> + FromConfig + Send + 'static,
 {
+    estimator.update(&*memory_usage);
+
+    if memory_config.component_tracking != ComponentTrackingMode::Full {
+        return;
+    }
+
     update_stats::<T, _>(&*memory_config, &*memory_usage, || {
-        MemoryStats::from_values_with_estimator(query.iter(), &E::from_config(&*memory_config))
+        measure_instances(
+            components.iter(),
+            estimator.get(&*memory_config),
+            &*memory_config,
+            &*memory_usage,
+        )
     });
 }
 
+/// This system updates the [`MemoryStats`] for the given component type `T`
+/// using the given [`DataSizeEstimator`] type, by only re-estimating the
+/// instances of `T` that were added or changed since the last update.
+///
+/// This only does anything if [`MemoryConfig::component_tracking`] is
+/// [`ComponentTrackingMode::Incremental`]. It should run in
+/// [`CoreStage::Last`], so that components removed by commands earlier in the
+/// frame are seen.
+///
+/// [`CoreStage::Last`]: bevy::app::CoreStage::Last
+pub fn update_stats_for_changed_components<T, E>(
+    all_components: Query<(Entity, &T)>,
+    changed_components: Query<Entity, Changed<T>>,
+    removed_components: RemovedComponents<T>,
+    mut cache: Local<InstanceCache<Entity>>,
+    mut estimator: Local<PersistentEstimator<E>>,
+    memory_config: Res<MemoryConfig>,
+    memory_usage: Res<MemoryUsage>,
+) where
+    T: Any + Component,
+    E: DataSizeEstimator<T> + FromConfig + Send + 'static,
+{
+    if estimator.update(&*memory_usage) {
+        // The cached estimates were made with the old config.
+        cache.invalidate();
+    }
+
+    if !is_tracking_enabled::<T>(&*memory_config)
+        || memory_config.component_tracking != ComponentTrackingMode::Incremental
+    {
+        // The cache will go stale while it is not being maintained.
+        cache.invalidate();
+        return;
+    }
+
+    if cache.is_valid() {
+        for entity in removed_components.iter() {
+            cache.remove(&entity);
+        }
+
+        // `Changed` also matches components that were just added.
+        for entity in changed_components.iter() {
+            cache.mark_changed(entity);
+        }
+
+        if !memory_usage.is_sampling() {
+            return;
+        }
+
+        let estimator = estimator.get(&*memory_config);
+
+        for entity in cache.take_changed() {
+            if let Ok((_entity, component)) = all_components.get(entity) {
+                let (stats, shared) = estimate_instance(component, estimator, &*memory_config);
+                cache.insert_with_shared(entity, stats, shared);
+            }
+        }
+
+        // Additions and removals keep the cache in sync with the entities
+        // that have a `T`, except for removals that happen after this system
+        // has run, which are cleared at the end of the frame without us ever
+        // seeing them. The length of an unfiltered query is computed from its
+        // archetypes, so this check is cheap, and the cache is only scanned
+        // for stale entries when it fails.
+        if cache.len() != all_components.iter().len() {
+            cache.retain(|entity| all_components.get(*entity).is_ok());
+        }
+    } else {
+        if !memory_usage.is_sampling() {
+            return;
+        }
+
+        let estimator = estimator.get(&*memory_config);
+
+        cache.rebuild_with_shared(all_components.iter().map(|(entity, component)| {
+            let (stats, shared) = estimate_instance(component, estimator, &*memory_config);
+
+            (entity, stats, shared)
+        }));
+    }
+
+    update_stats_from_cache::<T, _>(&*cache, &*memory_config, &*memory_usage);
+}
+
 /// This system updates the [`MemoryStats`] for the given resource type `T`
 /// using the given [`DataSizeEstimator`] type.
 pub fn update_stats_for_resource<T, E>(
     resource: Res<T>,
+    mut estimator: Local<PersistentEstimator<E>>,
     memory_config: Res<MemoryConfig>,
     memory_usage: Res<MemoryUsage>,
 ) where
     T: Any + Resource,
-    E: DataSizeEstimator<T> + FromConfig,
+    E: DataSizeEstimator<T> + FromConfig + Send + 'static,
 {
+    estimator.update(&*memory_usage);
+
     update_stats::<T, _>(&*memory_config, &*memory_usage, || {
-        MemoryStats::from_value_with_estimator(&*resource, &E::from_config(&*memory_config))
+        measure_values(
+            std::iter::once(&*resource),
+            estimator.get(&*memory_config),
+            &*memory_config,
+            &*memory_usage,
+        )
     });
 }
 
 /// This system updates the [`MemoryStats`] for the given asset type `T`
 /// using the given [`DataSizeEstimator`] type.
+///
+/// The estimate for each asset is cached, and only assets for which an
+/// [`AssetEvent`] was received since the last update are re-estimated.
+///
+/// The estimates for the individual assets are also made available through
+/// [`MemoryUsage::get_asset_stats`]. If there is an [`AssetServer`], the
+/// paths of the assets are recorded as well for
+/// [`MemoryUsage::asset_path_report`].
 pub fn update_stats_for_asset<T, E>(
     assets: Res<Assets<T>>,
+    mut asset_events: EventReader<AssetEvent<T>>,
+    mut cache: Local<InstanceCache<HandleId>>,
+    mut estimator: Local<PersistentEstimator<E>>,
+    asset_server: Option<Res<AssetServer>>,
     memory_config: Res<MemoryConfig>,
     memory_usage: Res<MemoryUsage>,
 ) where
     T: Any + Asset,
-    E: DataSizeEstimator<T> + FromConfig,
+    E: DataSizeEstimator<T> + FromConfig + Send + 'static,
 {
-    update_stats::<T, _>(&*memory_config, &*memory_usage, || {
-        MemoryStats::from_values_with_estimator(
-            assets.iter().map(|(_handle, asset)| asset),
-            &E::from_config(&*memory_config),
-        )
-    });
+    if estimator.update(&*memory_usage) {
+        // The cached estimates were made with the old config.
+        cache.invalidate();
+    }
+
+    if !is_tracking_enabled::<T>(&*memory_config) {
+        // The cache will go stale while it is not being maintained.
+        cache.invalidate();
+
+        // Stop publishing the stats of the individual assets as well, since
+        // they would go stale too.
+        if cache.take_modified() {
+            memory_usage.update_asset_stats_fast::<T>(HashMap::default());
+            memory_usage.update_asset_paths_fast::<T>(HashMap::default());
+        }
+
+        return;
+    }
+
+    if cache.is_valid() {
+        let mut removed = Vec::new();
+
+        for event in asset_events.iter() {
+            match event {
+                AssetEvent::Created { handle } | AssetEvent::Modified { handle } => {
+                    cache.mark_changed(handle.id);
+                }
+                AssetEvent::Removed { handle } => {
+                    cache.remove(&handle.id);
+                    removed.push(handle.id);
+                }
+            }
+        }
+
+        // Removals are published right away, so that the stats of unloaded
+        // assets are never reported.
+        if !removed.is_empty() {
+            memory_usage.update_changed_asset_stats_fast::<T, _>(
+                removed.iter().map(|handle_id| (*handle_id, None)),
+            );
+            memory_usage.update_changed_asset_paths_fast::<T, _>(
+                removed.iter().map(|handle_id| (*handle_id, None)),
+            );
+        }
+
+        if !memory_usage.is_sampling() {
+            return;
+        }
+
+        let estimator = estimator.get(&*memory_config);
+
+        let changed = cache.take_changed();
+        for handle_id in changed.iter() {
+            if let Some(asset) = assets.get(*handle_id) {
+                let (stats, shared) = estimate_instance(asset, estimator, &*memory_config);
+                cache.insert_with_shared(*handle_id, stats, shared);
+            }
+        }
+
+        // Only the assets that changed are published, instead of all of them.
+        memory_usage.update_changed_asset_stats_fast::<T, _>(
+            changed
+                .iter()
+                .map(|handle_id| (*handle_id, cache.get(handle_id))),
+        );
+
+        if let Some(asset_server) = asset_server {
+            memory_usage.update_changed_asset_paths_fast::<T, _>(changed.iter().map(|handle_id| {
+                let path = cache
+                    .get(handle_id)
+                    .and_then(|_| asset_path(&*asset_server, *handle_id));
+
+                (*handle_id, path)
+            }));
+        }
+    } else {
+        if !memory_usage.is_sampling() {
+            return;
+        }
+
+        // Any pending events are already reflected in the contents of
+        // `Assets<T>`, so they can be skipped.
+        for _event in asset_events.iter() {}
+
+        let estimator = estimator.get(&*memory_config);
+
+        cache.rebuild_with_shared(assets.iter().map(|(handle_id, asset)| {
+            let (stats, shared) = estimate_instance(asset, estimator, &*memory_config);
+
+            (handle_id, stats, shared)
+        }));
+
+        memory_usage.update_asset_stats_fast::<T>(cache.instances().clone());
+
+        if let Some(asset_server) = asset_server {
+            let asset_paths = cache
+                .instances()
+                .keys()
+                .filter_map(|handle_id| Some((*handle_id, asset_path(&*asset_server, *handle_id)?)))
+                .collect();
+
+            memory_usage.update_asset_paths_fast::<T>(asset_paths);
+        }
+    }
+
+    update_stats_from_cache::<T, _>(&*cache, &*memory_config, &*memory_usage);
+
+    // Everything is published by now, so the flag is only left to tell
+    // whether there is anything to clear when tracking gets disabled.
+    cache.take_modified();
+}
+
+/// Returns the path that the asset with the given handle was loaded from.
+fn asset_path(asset_server: &AssetServer, handle_id: HandleId) -> Option<PathBuf> {
+    let path = asset_server.get_handle_path(handle_id)?;
+
+    Some(path.path().to_path_buf())
 }
 
 /// This system updates the [`MemoryStats`] for the given render asset type `T`
@@ -73,30 +365,95 @@ pub fn update_stats_for_asset<T, E>(
 #[cfg(feature = "bevy_render")]
 pub fn update_stats_for_render_asset<T, E>(
     render_assets: Res<RenderAssets<T>>,
+    mut estimator: Local<PersistentEstimator<E>>,
     memory_config: Res<MemoryConfig>,
     memory_usage: Res<MemoryUsage>,
 ) where
     T: RenderAsset,
     <T as RenderAsset>::PreparedAsset: Any,
-    E: DataSizeEstimator<<T as RenderAsset>::PreparedAsset> + FromConfig,
+    E: DataSizeEstimator<<T as RenderAsset>::PreparedAsset> + FromConfig + Send + 'static,
 {
+    estimator.update(&*memory_usage);
+
     update_stats::<<T as RenderAsset>::PreparedAsset, _>(&*memory_config, &*memory_usage, || {
-        MemoryStats::from_values_with_estimator(
-            render_assets.iter().map(|(_handle, asset)| asset),
-            &E::from_config(&*memory_config),
+        measure_instances(
+            render_assets
+                .iter()
+                .map(|(handle, asset)| (handle.id, asset)),
+            estimator.get(&*memory_config),
+            &*memory_config,
+            &*memory_usage,
         )
     });
 }
 
+/// This system updates the [`MemoryStats`] of the [`ExtractedAssets`] of the
+/// given render asset type `T` using the given [`DataSizeEstimator`].
+///
+/// Every asset that changed since the last frame is copied into its extracted
+/// form and queued in the render world to be prepared. This system estimates
+/// the size of that transient copy from the assets it is copied from, so that
+/// they do not have to be extracted a second time. The stack size is that of
+/// `<T as RenderAsset>::ExtractedAsset`, but the heap and device sizes are
+/// the ones that the estimator returns for the source asset `T`, which is
+/// only accurate if the extracted asset holds the same data.
+///
+/// This should be added to the [`RenderStage::Extract`] stage of the render
+/// sub-app, where it runs on the main world.
+///
+/// [`RenderStage::Extract`]: bevy::render::RenderStage::Extract
+#[cfg(feature = "bevy_render")]
+pub fn update_stats_for_extracted_render_asset<T, E>(
+    assets: Res<Assets<T>>,
+    mut asset_events: EventReader<AssetEvent<T>>,
+    mut estimator: Local<PersistentEstimator<E>>,
+    memory_config: Res<MemoryConfig>,
+    memory_usage: Res<MemoryUsage>,
+) where
+    T: RenderAsset,
+    E: DataSizeEstimator<T> + FromConfig + Send + 'static,
+{
+    estimator.update(&*memory_usage);
+
+    // Mirrors the way that Bevy decides which assets to extract.
+    let mut changed_assets = HashSet::default();
+
+    for event in asset_events.iter() {
+        match event {
+            AssetEvent::Created { handle } | AssetEvent::Modified { handle } => {
+                changed_assets.insert(handle.id);
+            }
+            AssetEvent::Removed { handle } => {
+                changed_assets.remove(&handle.id);
+            }
+        }
+    }
+
+    update_stats::<ExtractedAssets<T>, _>(&*memory_config, &*memory_usage, || {
+        let mut stats = MemoryStats::from_values_with_estimator(
+            changed_assets
+                .into_iter()
+                .filter_map(|handle_id| assets.get(handle_id)),
+            estimator.get(&*memory_config),
+        );
+
+        stats.total_stack_bytes =
+            stats.count * std::mem::size_of::<<T as RenderAsset>::ExtractedAsset>();
+
+        stats
+    });
+}
+
 /// A helper function to update [`MemoryStats`] using a closure.
 ///
-/// Checks the [`MemoryConfig`] first before calling the closure.
+/// Checks the [`MemoryConfig`] and whether stats are being sampled during the
+/// current frame before calling the closure.
 pub fn update_stats<T, F>(memory_config: &MemoryConfig, memory_usage: &MemoryUsage, op: F)
 where
     T: Any,
     F: FnOnce() -> MemoryStats,
 {
-    if !memory_config.global {
+    if !is_tracking_enabled::<T>(memory_config) || !memory_usage.is_sampling() {
         return;
     }
 
@@ -104,3 +461,1085 @@ where
 
     memory_usage.update_stats_fast::<T>(stats);
 }
+
+/// Computes the [`MemoryStats`] of the given values.
+///
+/// If [`MemoryConfig::size_distributions`] is enabled, this also updates the
+/// [`SizeDistribution`] of `T`. If
+/// [`MemoryConfig::deduplicate_shared_allocations`] is enabled, shared
+/// allocations are only counted once and their size is recorded as well.
+pub fn measure_values<'a, T, E, I>(
+    values: I,
+    estimator: &E,
+    memory_config: &MemoryConfig,
+    memory_usage: &MemoryUsage,
+) -> MemoryStats
+where
+    T: Any,
+    E: DataSizeEstimator<T>,
+    I: IntoIterator<Item = &'a T>,
+{
+    if !memory_config.deduplicate_shared_allocations {
+        if !memory_config.size_distributions {
+            return MemoryStats::from_values_with_estimator(values, estimator);
+        }
+
+        let instances: Vec<MemoryStats> = values
+            .into_iter()
+            .map(|value| MemoryStats::from_value_with_estimator(value, estimator))
+            .collect();
+
+        memory_usage.update_distribution_fast::<T>(SizeDistribution::from_instances(
+            instances.iter().copied(),
+        ));
+
+        return instances.into_iter().sum();
+    }
+
+    let mut shared = SharedAllocations::default();
+
+    let instances: Vec<MemoryStats> = values
+        .into_iter()
+        .map(|value| estimate_sharing(value, estimator, &mut shared))
+        .collect();
+
+    if memory_config.size_distributions {
+        memory_usage.update_distribution_fast::<T>(SizeDistribution::from_instances(
+            instances.iter().copied(),
+        ));
+    }
+
+    memory_usage.update_shared_bytes_fast::<T>(shared.shared_bytes());
+
+    sum_deduplicated(instances.into_iter(), &mut shared)
+}
+
+/// Like [`measure_values`], but each value is identified by a key.
+///
+/// If [`MemoryConfig::largest_instances`] is non-zero, this also updates the
+/// largest instances of `T`.
+pub fn measure_instances<'a, T, E, K, I>(
+    instances: I,
+    estimator: &E,
+    memory_config: &MemoryConfig,
+    memory_usage: &MemoryUsage,
+) -> MemoryStats
+where
+    T: Any,
+    E: DataSizeEstimator<T>,
+    K: Into<InstanceId>,
+    I: IntoIterator<Item = (K, &'a T)>,
+{
+    if memory_config.largest_instances == 0 {
+        return measure_values(
+            instances.into_iter().map(|(_key, value)| value),
+            estimator,
+            memory_config,
+            memory_usage,
+        );
+    }
+
+    let mut shared = memory_config
+        .deduplicate_shared_allocations
+        .then(SharedAllocations::default);
+
+    let instances: Vec<InstanceStats> = instances
+        .into_iter()
+        .map(|(key, value)| InstanceStats {
+            id: key.into(),
+            stats: match &mut shared {
+                Some(shared) => estimate_sharing(value, estimator, shared),
+                None => MemoryStats::from_value_with_estimator(value, estimator),
+            },
+        })
+        .collect();
+
+    update_instance_stats::<T, _>(instances.iter().copied(), memory_config, memory_usage);
+
+    let instances = instances.into_iter().map(|instance| instance.stats);
+
+    match &mut shared {
+        Some(shared) => {
+            memory_usage.update_shared_bytes_fast::<T>(shared.shared_bytes());
+
+            sum_deduplicated(instances, shared)
+        }
+        None => instances.sum(),
+    }
+}
+
+/// Estimates a single value, recording the shared allocations that it holds
+/// in `shared`.
+///
+/// Like with [`estimate_instance`], the returned stats include the full size
+/// of those allocations, no matter how many other values hold them. Only the
+/// sum from [`sum_deduplicated`] counts each of them once.
+fn estimate_sharing<T, E>(value: &T, estimator: &E, shared: &mut SharedAllocations) -> MemoryStats
+where
+    T: Any,
+    E: DataSizeEstimator<T>,
+{
+    let mut instance_shared = SharedAllocations::default();
+    let stats =
+        MemoryStats::from_value_with_shared_allocations(value, estimator, &mut instance_shared);
+
+    for (address, size) in instance_shared.iter() {
+        shared.visit(address, size);
+    }
+
+    stats
+}
+
+/// Sums the stats of values from [`estimate_sharing`], counting each of the
+/// shared allocations in `shared` only once.
+fn sum_deduplicated<I>(instances: I, shared: &mut SharedAllocations) -> MemoryStats
+where
+    I: Iterator<Item = MemoryStats>,
+{
+    let mut total: MemoryStats = instances.sum();
+    total.total_heap_bytes -= shared.take_duplicate_bytes();
+
+    total
+}
+
+/// Estimates a single value to be stored in an [`InstanceCache`].
+///
+/// If [`MemoryConfig::deduplicate_shared_allocations`] is enabled, the shared
+/// allocations held by the value are returned as well, so that the cache can
+/// count each of them only once across all instances.
+pub fn estimate_instance<T, E>(
+    value: &T,
+    estimator: &E,
+    memory_config: &MemoryConfig,
+) -> (MemoryStats, SharedAllocations)
+where
+    T: Any,
+    E: DataSizeEstimator<T>,
+{
+    let mut shared = SharedAllocations::default();
+
+    let stats = if memory_config.deduplicate_shared_allocations {
+        MemoryStats::from_value_with_shared_allocations(value, estimator, &mut shared)
+    } else {
+        MemoryStats::from_value_with_estimator(value, estimator)
+    };
+
+    (stats, shared)
+}
+
+/// Updates the [`MemoryStats`] of `T` from the contents of an
+/// [`InstanceCache`].
+///
+/// This also updates the [`SizeDistribution`] and the largest instances of
+/// `T` if they are enabled in the [`MemoryConfig`].
+pub fn update_stats_from_cache<T, K>(
+    cache: &InstanceCache<K>,
+    memory_config: &MemoryConfig,
+    memory_usage: &MemoryUsage,
+) where
+    T: Any,
+    K: Copy + Eq + Hash + Into<InstanceId>,
+{
+    memory_usage.update_stats_fast::<T>(cache.total());
+
+    if memory_config.deduplicate_shared_allocations {
+        memory_usage.update_shared_bytes_fast::<T>(cache.shared_bytes());
+    }
+
+    update_instance_stats::<T, _>(
+        cache.iter().map(|(key, stats)| InstanceStats {
+            id: (*key).into(),
+            stats,
+        }),
+        memory_config,
+        memory_usage,
+    );
+}
+
+/// Updates the per-instance statistics of `T` that are enabled in the
+/// [`MemoryConfig`].
+fn update_instance_stats<T, I>(
+    instances: I,
+    memory_config: &MemoryConfig,
+    memory_usage: &MemoryUsage,
+) where
+    T: Any,
+    I: Iterator<Item = InstanceStats> + Clone,
+{
+    if memory_config.size_distributions {
+        memory_usage.update_distribution_fast::<T>(SizeDistribution::from_instances(
+            instances.clone().map(|instance| instance.stats),
+        ));
+    }
+
+    if memory_config.largest_instances > 0 {
+        memory_usage.update_largest_instances_fast::<T>(largest_instances(
+            instances,
+            memory_config.largest_instances,
+        ));
+    }
+}
+
+/// Returns `true` if the [`MemoryConfig`] allows updating the [`MemoryStats`]
+/// for the given type.
+pub fn is_tracking_enabled<T>(memory_config: &MemoryConfig) -> bool
+where
+    T: Any,
+{
+    memory_config.is_enabled::<T>()
+}
+
+/// Caches the [`MemoryStats`] of individual instances of a type, keyed by `K`,
+/// along with their running total.
+///
+/// This is used by systems that only re-estimate the instances that changed.
+/// Other per-instance values can be cached instead by implementing
+/// [`CachedValue`] for them.
+#[derive(Debug)]
+pub struct InstanceCache<K, V = MemoryStats> {
+    valid: bool,
+    instances: HashMap<K, V>,
+    changed: HashSet<K>,
+    total: V,
+    modified: bool,
+
+    // The address and size of the shared allocations held by each instance.
+    instance_shared: HashMap<K, Vec<(usize, usize)>>,
+    // Maps the address of each shared allocation to the number of instances
+    // holding it and its size.
+    shared: HashMap<usize, (usize, usize)>,
+    shared_bytes: usize,
+    duplicate_bytes: usize,
+}
+
+impl<K, V> Default for InstanceCache<K, V>
+where
+    V: Default,
+{
+    fn default() -> Self {
+        Self {
+            valid: false,
+            instances: Default::default(),
+            changed: Default::default(),
+            total: Default::default(),
+            modified: false,
+            instance_shared: Default::default(),
+            shared: Default::default(),
+            shared_bytes: 0,
+            duplicate_bytes: 0,
+        }
+    }
+}
+
+impl<K, V> InstanceCache<K, V>
+where
+    K: Clone + Eq + Hash,
+    V: CachedValue,
+{
+    /// Returns `false` if the cache needs to be rebuilt from scratch.
+    #[inline]
+    pub fn is_valid(&self) -> bool {
+        self.valid
+    }
+
+    /// Clears the cache and marks it as needing to be rebuilt.
+    pub fn invalidate(&mut self) {
+        if self.valid {
+            self.valid = false;
+            self.clear();
+            self.modified = true;
+        }
+    }
+
+    /// Replaces the contents of the cache with the given instances and marks
+    /// the cache as valid.
+    pub fn rebuild<I>(&mut self, instances: I)
+    where
+        I: IntoIterator<Item = (K, V)>,
+    {
+        self.rebuild_with_shared(
+            instances
+                .into_iter()
+                .map(|(key, value)| (key, value, SharedAllocations::default())),
+        );
+    }
+
+    /// Like [`rebuild`][Self::rebuild], but also takes the shared allocations
+    /// held by each instance.
+    ///
+    /// See [`insert_with_shared`][Self::insert_with_shared].
+    pub fn rebuild_with_shared<I>(&mut self, instances: I)
+    where
+        I: IntoIterator<Item = (K, V, SharedAllocations)>,
+    {
+        self.clear();
+
+        for (key, value, shared) in instances {
+            self.insert_with_shared(key, value, shared);
+        }
+
+        self.valid = true;
+        self.modified = true;
+    }
+
+    /// Inserts or replaces the value of a single instance.
+    pub fn insert(&mut self, key: K, value: V) {
+        self.insert_with_shared(key, value, SharedAllocations::default());
+    }
+
+    /// Inserts or replaces the value of a single instance, along with the
+    /// shared allocations that are included in its heap size.
+    ///
+    /// Each shared allocation is only counted once in the
+    /// [`total`][Self::total], no matter how many instances hold it.
+    pub fn insert_with_shared(&mut self, key: K, value: V, shared: SharedAllocations) {
+        value.add_to(&mut self.total);
+
+        if let Some(old_value) = self.instances.insert(key.clone(), value) {
+            old_value.sub_from(&mut self.total);
+        }
+
+        self.modified = true;
+
+        self.release_shared(&key);
+
+        if !shared.is_empty() {
+            let allocations: Vec<_> = shared.iter().collect();
+
+            for (address, size) in allocations.iter().copied() {
+                let (holders, _) = self.shared.entry(address).or_insert((0, size));
+                *holders += 1;
+
+                if *holders == 1 {
+                    self.shared_bytes += size;
+                } else {
+                    self.duplicate_bytes += size;
+                }
+            }
+
+            self.instance_shared.insert(key, allocations);
+        }
+    }
+
+    /// Marks a single instance as needing to be re-estimated.
+    ///
+    /// The instance will be returned by the next call to
+    /// [`take_changed`][Self::take_changed].
+    pub fn mark_changed(&mut self, key: K) {
+        self.changed.insert(key);
+    }
+
+    /// Returns all of the instances that were marked as changed since the
+    /// last call.
+    pub fn take_changed(&mut self) -> HashSet<K> {
+        std::mem::take(&mut self.changed)
+    }
+
+    /// Removes a single instance, returning its value if it was cached.
+    pub fn remove(&mut self, key: &K) -> Option<V> {
+        self.changed.remove(key);
+
+        let old_value = self.instances.remove(key)?;
+
+        old_value.sub_from(&mut self.total);
+        self.modified = true;
+        self.release_shared(key);
+
+        Some(old_value)
+    }
+
+    /// Removes all instances for which `keep` returns `false`.
+    pub fn retain<F>(&mut self, mut keep: F)
+    where
+        F: FnMut(&K) -> bool,
+    {
+        let total = &mut self.total;
+        let modified = &mut self.modified;
+        let mut removed = Vec::new();
+
+        self.instances.retain(|key, value| {
+            let keep = keep(key);
+            if !keep {
+                value.sub_from(total);
+                *modified = true;
+
+                if !self.instance_shared.is_empty() {
+                    removed.push(key.clone());
+                }
+            }
+            keep
+        });
+
+        for key in removed {
+            self.release_shared(&key);
+        }
+    }
+
+    /// Returns `true` if any instances were inserted or removed since the last
+    /// call.
+    pub fn take_modified(&mut self) -> bool {
+        std::mem::take(&mut self.modified)
+    }
+
+    /// Returns the value of a single cached instance.
+    #[inline]
+    pub fn get(&self, key: &K) -> Option<V> {
+        self.instances.get(key).cloned()
+    }
+
+    /// Returns an iterator over the keys and values of all cached instances.
+    pub fn iter(&self) -> impl Iterator<Item = (&K, V)> + Clone {
+        self.instances
+            .iter()
+            .map(|(key, value)| (key, value.clone()))
+    }
+
+    /// Returns the values of all cached instances.
+    #[inline]
+    pub fn instances(&self) -> &HashMap<K, V> {
+        &self.instances
+    }
+
+    /// Returns the number of cached instances.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.instances.len()
+    }
+
+    /// Returns `true` if there are no cached instances.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.instances.is_empty()
+    }
+
+    /// Returns the sum of the values of all cached instances.
+    ///
+    /// Shared allocations that were passed to
+    /// [`insert_with_shared`][Self::insert_with_shared] are only counted once.
+    #[inline]
+    pub fn total(&self) -> V {
+        self.total.without_duplicates(self.duplicate_bytes)
+    }
+
+    /// Returns the total size of the distinct shared allocations held by the
+    /// cached instances.
+    #[inline]
+    pub fn shared_bytes(&self) -> usize {
+        self.shared_bytes
+    }
+
+    fn clear(&mut self) {
+        self.instances.clear();
+        self.changed.clear();
+        self.total = V::default();
+        self.instance_shared.clear();
+        self.shared.clear();
+        self.shared_bytes = 0;
+        self.duplicate_bytes = 0;
+    }
+
+    /// Stops counting the shared allocations held by the given instance.
+    fn release_shared(&mut self, key: &K) {
+        let allocations = match self.instance_shared.remove(key) {
+            Some(allocations) => allocations,
+            None => return,
+        };
+
+        for (address, size) in allocations {
+            let holders = match self.shared.get_mut(&address) {
+                Some((holders, _)) => holders,
+                None => continue,
+            };
+
+            *holders -= 1;
+
+            if *holders == 0 {
+                self.shared.remove(&address);
+                self.shared_bytes -= size;
+            } else {
+                self.duplicate_bytes -= size;
+            }
+        }
+    }
+}
+
+/// A value that can be cached for each instance by an [`InstanceCache`], which
+/// keeps a running total of the values of all instances.
+pub trait CachedValue: Clone + Default {
+    /// Adds this value to the given total.
+    fn add_to(&self, total: &mut Self);
+
+    /// Subtracts this value, which was previously added, from the given total.
+    fn sub_from(&self, total: &mut Self);
+
+    /// Returns a copy of a total without the given number of heap bytes of
+    /// shared allocations that were counted more than once.
+    fn without_duplicates(&self, duplicate_bytes: usize) -> Self;
+}
+
+impl CachedValue for MemoryStats {
+    #[inline]
+    fn add_to(&self, total: &mut Self) {
+        *total = *total + *self;
+    }
+
+    #[inline]
+    fn sub_from(&self, total: &mut Self) {
+        *total = *total - *self;
+    }
+
+    #[inline]
+    fn without_duplicates(&self, duplicate_bytes: usize) -> Self {
+        let mut total = *self;
+        total.total_heap_bytes = total.total_heap_bytes.saturating_sub(duplicate_bytes);
+
+        total
+    }
+}
+
+/// Holds an estimator of type `E` across runs of a system.
+///
+/// The estimator is created with [`FromConfig`] on first use, and is only
+/// recreated after the [`MemoryConfig`] changes. This allows estimators to
+/// precompute state from the config, or to keep caches that are reused
+/// between measurements.
+///
+/// Systems should call [`update`][Self::update] at the start of every run, so
+/// that no change to the config is missed.
+pub struct PersistentEstimator<E> {
+    generation: Option<u64>,
+
+    // Only ever accessed through `&mut self`, so this never blocks. It allows
+    // estimators that are not `Sync` to be stored in a `Local`.
+    estimator: Mutex<Option<E>>,
+}
+
+impl<E> Default for PersistentEstimator<E> {
+    fn default() -> Self {
+        Self {
+            generation: None,
+            estimator: Mutex::new(None),
+        }
+    }
+}
+
+impl<E> PersistentEstimator<E>
+where
+    E: FromConfig,
+{
+    /// Discards the estimator if the [`MemoryConfig`] changed since the last
+    /// call.
+    ///
+    /// Returns `true` if an estimator that may have been used was discarded,
+    /// in which case any estimates that were cached from it are stale.
+    pub fn update(&mut self, memory_usage: &MemoryUsage) -> bool {
+        self.update_generation(memory_usage.config_generation())
+    }
+
+    /// Like [`update`][Self::update], but takes the config generation
+    /// directly.
+    pub(crate) fn update_generation(&mut self, generation: u64) -> bool {
+        if self.generation == Some(generation) {
+            return false;
+        }
+
+        let was_used = self.generation.replace(generation).is_some();
+        *self.estimator.get_mut() = None;
+
+        was_used
+    }
+
+    /// Returns the estimator, creating it first if necessary.
+    pub fn get(&mut self, memory_config: &MemoryConfig) -> &E {
+        self.estimator
+            .get_mut()
+            .get_or_insert_with(|| E::from_config(memory_config))
+    }
+}
+
+/***************************************************************************************************
+
+                             dMMMMMMP dMMMMMP .dMMMb dMMMMMMP .dMMMb
+                               dMP   dMP     dMP" VP   dMP   dMP" VP
+                              dMP   dMMMP    VMMMb    dMP    VMMMb
+                             dMP   dMP     dP .dMP   dMP   dP .dMP
+                            dMP   dMMMMMP  VMMMP"   dMP    VMMMP"
+
+***************************************************************************************************/
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::{sync::Arc, time::Duration};
+
+    use bevy::{
+        app::App,
+        asset::{AddAsset, AssetPlugin, Handle},
+        core::CorePlugin,
+        ecs::{
+            schedule::{Stage, SystemStage},
+            world::{Mut, World},
+        },
+        reflect::TypeUuid,
+    };
+
+    use crate::{
+        estimator::{ForwardingEstimator, SharedForwardingEstimator},
+        DataSize, MemoryUsagePlugin, RegisterSizedTypes, TypeKind, VisitSharedAllocations,
+    };
+
+    fn stats(total_heap_bytes: usize) -> MemoryStats {
+        MemoryStats {
+            count: 1,
+            total_stack_bytes: 8,
+            total_heap_bytes,
+            total_device_bytes: 0,
+        }
+    }
+
+    #[test]
+    fn instance_cache_keeps_running_total() {
+        let mut cache = InstanceCache::<u32>::default();
+        assert!(!cache.is_valid());
+
+        cache.rebuild([(1, stats(100)), (2, stats(200))]);
+        assert!(cache.is_valid());
+        assert!(cache.take_modified());
+        assert_eq!(cache.total(), stats(100) + stats(200));
+
+        // Inserting a new instance adds to the total.
+        cache.insert(3, stats(50));
+        assert_eq!(cache.len(), 3);
+        assert_eq!(cache.total().total_heap_bytes, 350);
+
+        // Updating an instance replaces its old stats.
+        cache.insert(1, stats(10));
+        assert_eq!(cache.len(), 3);
+        assert_eq!(cache.get(&1), Some(stats(10)));
+        assert_eq!(cache.total().count, 3);
+        assert_eq!(cache.total().total_heap_bytes, 260);
+
+        // Removing an instance subtracts it from the total.
+        assert_eq!(cache.remove(&2), Some(stats(200)));
+        assert_eq!(cache.remove(&2), None);
+        assert_eq!(cache.len(), 2);
+        assert_eq!(cache.total(), stats(10) + stats(50));
+        assert!(cache.take_modified());
+        assert!(!cache.take_modified());
+    }
+
+    #[test]
+    fn instance_cache_tracks_changed_instances() {
+        let mut cache = InstanceCache::<u32>::default();
+        cache.rebuild([(1, stats(100)), (2, stats(200))]);
+
+        cache.mark_changed(1);
+        cache.mark_changed(2);
+        cache.remove(&2);
+
+        let changed: Vec<_> = cache.take_changed().into_iter().collect();
+        assert_eq!(changed, vec![1]);
+        assert!(cache.take_changed().is_empty());
+    }
+
+    #[test]
+    fn instance_cache_retain_and_invalidate() {
+        let mut cache = InstanceCache::<u32>::default();
+        cache.rebuild([(1, stats(100)), (2, stats(200)), (3, stats(300))]);
+
+        cache.retain(|key| *key != 2);
+        assert_eq!(cache.len(), 2);
+        assert_eq!(cache.total().total_heap_bytes, 400);
+
+        cache.invalidate();
+        assert!(!cache.is_valid());
+        assert!(cache.is_empty());
+        assert_eq!(cache.total(), MemoryStats::default());
+    }
+
+    #[derive(Component)]
+    struct Terrain {
+        heights: Arc<Vec<u8>>,
+    }
+
+    impl DataSize for Terrain {
+        const IS_DYNAMIC: bool = true;
+        const STATIC_HEAP_SIZE: usize = 0;
+
+        fn estimate_heap_size(&self) -> usize {
+            std::mem::size_of::<Vec<u8>>() + self.heights.estimate_heap_size()
+        }
+    }
+
+    impl VisitSharedAllocations for Terrain {
+        fn visit_shared_allocations(&self, shared: &mut SharedAllocations) {
+            self.heights.visit_shared_allocations(shared);
+        }
+    }
+
+    #[test]
+    fn instance_cache_counts_shared_allocations_once() {
+        let heights = Arc::new(vec![0u8; 100]);
+        let terrains: Vec<_> = (0..3)
+            .map(|_| Terrain {
+                heights: heights.clone(),
+            })
+            .collect();
+
+        let memory_config = MemoryConfig {
+            deduplicate_shared_allocations: true,
+            ..Default::default()
+        };
+
+        let mut cache = InstanceCache::<u32>::default();
+        cache.rebuild_with_shared(terrains.iter().zip(0..).map(|(terrain, key)| {
+            let (stats, shared) =
+                estimate_instance(terrain, &SharedForwardingEstimator, &memory_config);
+
+            (key, stats, shared)
+        }));
+
+        // Each instance holds the whole payload on its own.
+        assert_eq!(cache.get(&0).unwrap().total_heap_bytes, 124);
+        assert_eq!(cache.total().count, 3);
+        assert_eq!(cache.total().total_heap_bytes, 124);
+        assert_eq!(cache.shared_bytes(), 124);
+
+        // Re-estimating an instance does not count the payload again.
+        let (stats, shared) =
+            estimate_instance(&terrains[1], &SharedForwardingEstimator, &memory_config);
+        cache.insert_with_shared(1, stats, shared);
+        assert_eq!(cache.total().total_heap_bytes, 124);
+
+        cache.remove(&0);
+        cache.retain(|key| *key != 1);
+        assert_eq!(cache.total().total_heap_bytes, 124);
+        assert_eq!(cache.shared_bytes(), 124);
+
+        cache.remove(&2);
+        assert_eq!(cache.total(), MemoryStats::default());
+        assert_eq!(cache.shared_bytes(), 0);
+    }
+
+    fn measure_terrains(component_tracking: ComponentTrackingMode) -> MemoryUsage {
+        let mut world = World::new();
+
+        let mut memory_usage = MemoryUsage::default();
+        memory_usage.register_type_with_kind::<Terrain>(TypeKind::Component);
+        world.insert_resource(memory_usage);
+        world.insert_resource(MemoryConfig {
+            deduplicate_shared_allocations: true,
+            component_tracking,
+            size_distributions: true,
+            largest_instances: 3,
+            ..Default::default()
+        });
+
+        let heights = Arc::new(vec![0u8; 100]);
+        for _ in 0..3 {
+            world.spawn().insert(Terrain {
+                heights: heights.clone(),
+            });
+        }
+
+        let mut stage = SystemStage::parallel();
+        stage.add_system(update_stats_for_component::<Terrain, SharedForwardingEstimator>);
+        stage.add_system(update_stats_for_changed_components::<Terrain, SharedForwardingEstimator>);
+        stage.run(&mut world);
+
+        world.get_resource::<MemoryUsage>().unwrap().clone()
+    }
+
+    #[test]
+    fn components_sharing_an_arc_count_its_payload_once() {
+        for component_tracking in [
+            ComponentTrackingMode::Full,
+            ComponentTrackingMode::Incremental,
+        ] {
+            let memory_usage = measure_terrains(component_tracking);
+
+            let stats = memory_usage.get_stats::<Terrain>().unwrap();
+            assert_eq!(stats.count, 3);
+            assert_eq!(stats.total_heap_bytes, 124, "{component_tracking:?}");
+
+            let shared_bytes = memory_usage.get_shared_bytes::<Terrain>().unwrap();
+            assert_eq!(shared_bytes, 124, "{component_tracking:?}");
+
+            // Each instance holds the whole payload on its own.
+            for instance in memory_usage.get_largest_instances::<Terrain>().unwrap() {
+                assert_eq!(
+                    instance.stats.total_heap_bytes, 124,
+                    "{component_tracking:?}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn shared_allocations_are_attributed_alike_in_all_tracking_modes() {
+        let full = measure_terrains(ComponentTrackingMode::Full);
+        let incremental = measure_terrains(ComponentTrackingMode::Incremental);
+
+        let instance_stats = |memory_usage: &MemoryUsage| -> Vec<MemoryStats> {
+            memory_usage
+                .get_largest_instances::<Terrain>()
+                .unwrap()
+                .iter()
+                .map(|instance| instance.stats)
+                .collect()
+        };
+
+        assert_eq!(instance_stats(&full), instance_stats(&incremental));
+        assert_eq!(
+            full.get_distribution::<Terrain>(),
+            incremental.get_distribution::<Terrain>()
+        );
+        assert_eq!(
+            full.get_stats::<Terrain>(),
+            incremental.get_stats::<Terrain>()
+        );
+    }
+
+    #[derive(DataSize, TypeUuid)]
+    #[uuid = "5c0e7a51-2b8e-4f0f-a0d4-8f1e3c6b2d01"]
+    struct Blob {
+        data: Vec<u8>,
+    }
+
+    fn blob(len: usize) -> Blob {
+        Blob { data: vec![0; len] }
+    }
+
+    fn blob_app(sampling: SamplingPolicy) -> App {
+        let mut app = App::new();
+        app.add_plugin(CorePlugin)
+            .add_plugin(AssetPlugin)
+            .add_asset::<Blob>()
+            .insert_resource(MemoryConfig {
+                sampling,
+                ..Default::default()
+            })
+            .add_plugin(MemoryUsagePlugin)
+            .register_sized_asset::<Blob>();
+
+        app
+    }
+
+    fn blobs(app: &mut App) -> Mut<Assets<Blob>> {
+        app.world.get_resource_mut::<Assets<Blob>>().unwrap()
+    }
+
+    fn memory_usage(app: &App) -> &MemoryUsage {
+        app.world.get_resource::<MemoryUsage>().unwrap()
+    }
+
+    /// Measures every blob from scratch.
+    fn rescan_blobs(app: &App) -> MemoryStats {
+        let blobs = app.world.get_resource::<Assets<Blob>>().unwrap();
+
+        MemoryStats::from_values_with_estimator(
+            blobs.iter().map(|(_, blob)| blob),
+            &ForwardingEstimator,
+        )
+    }
+
+    fn blob_stats(app: &App, handle: &Handle<Blob>) -> Option<MemoryStats> {
+        memory_usage(app).get_asset_stats::<Blob>(handle)
+    }
+
+    #[test]
+    fn asset_events_keep_stats_in_sync_with_assets() {
+        let mut app = blob_app(SamplingPolicy::EveryNFrames(2));
+
+        let a = blobs(&mut app).add(blob(100));
+        let b = blobs(&mut app).add(blob(200));
+
+        // The first frame is sampled, and builds the cache from scratch.
+        app.update();
+        assert_eq!(
+            memory_usage(&app).get_stats::<Blob>(),
+            Some(rescan_blobs(&app))
+        );
+        assert_eq!(blob_stats(&app, &b).unwrap().total_heap_bytes, 200);
+
+        // These events arrive on a frame that is not sampled.
+        blobs(&mut app).get_mut(&a).unwrap().data = vec![0; 300];
+        let c = blobs(&mut app).add(blob(400));
+        app.update();
+        assert!(blob_stats(&app, &c).is_none());
+
+        // Removals are published right away, even without sampling.
+        blobs(&mut app).remove(&b);
+        app.update();
+        assert!(blob_stats(&app, &b).is_none());
+        assert_eq!(
+            memory_usage(&app).get_stats::<Blob>(),
+            Some(rescan_blobs(&app))
+        );
+        assert_eq!(blob_stats(&app, &a).unwrap().total_heap_bytes, 300);
+        assert_eq!(blob_stats(&app, &c).unwrap().total_heap_bytes, 400);
+
+        let mut memory_config = app.world.get_resource_mut::<MemoryConfig>().unwrap();
+        memory_config.disable::<Blob>();
+        app.update();
+        assert!(blob_stats(&app, &a).is_none());
+
+        // Changes made while tracking is disabled are picked up by the
+        // rebuild once it is enabled again.
+        blobs(&mut app).remove(&a);
+        blobs(&mut app).get_mut(&c).unwrap().data = vec![0; 500];
+        let d = blobs(&mut app).add(blob(600));
+
+        let mut memory_config = app.world.get_resource_mut::<MemoryConfig>().unwrap();
+        memory_config.enable::<Blob>();
+        app.update();
+        app.update();
+
+        assert_eq!(
+            memory_usage(&app).get_stats::<Blob>(),
+            Some(rescan_blobs(&app))
+        );
+        assert!(blob_stats(&app, &a).is_none());
+        assert_eq!(blob_stats(&app, &c).unwrap().total_heap_bytes, 500);
+        assert_eq!(blob_stats(&app, &d).unwrap().total_heap_bytes, 600);
+    }
+
+    #[derive(DataSize)]
+    struct Buffer {
+        data: Vec<u8>,
+    }
+
+    /// Runs the given number of frames, growing the buffer by one byte per
+    /// frame, and returns the frames during which its stats were updated.
+    ///
+    /// A sample is requested right before each of the `requested` frames.
+    fn sampled_frames(sampling: SamplingPolicy, frames: usize, requested: &[usize]) -> Vec<usize> {
+        let mut world = World::new();
+
+        let mut memory_usage = MemoryUsage::default();
+        memory_usage.register_type_with_kind::<Buffer>(TypeKind::Resource);
+        world.insert_resource(memory_usage);
+        world.insert_resource(MemoryConfig {
+            sampling,
+            ..Default::default()
+        });
+        world.insert_resource(Buffer { data: Vec::new() });
+
+        let mut first_stage = SystemStage::parallel();
+        first_stage.add_system(begin_frame);
+
+        let mut update_stage = SystemStage::parallel();
+        update_stage.add_system(update_stats_for_resource::<Buffer, ForwardingEstimator>);
+
+        let mut sampled = Vec::new();
+
+        for frame in 1..=frames {
+            world.get_resource_mut::<Buffer>().unwrap().data = vec![0; frame];
+
+            if requested.contains(&frame) {
+                world
+                    .get_resource::<MemoryUsage>()
+                    .unwrap()
+                    .request_sample();
+            }
+
+            first_stage.run(&mut world);
+            update_stage.run(&mut world);
+
+            let memory_usage = world.get_resource::<MemoryUsage>().unwrap();
+            if memory_usage.get_stats::<Buffer>().unwrap().total_heap_bytes == frame {
+                sampled.push(frame);
+            }
+        }
+
+        sampled
+    }
+
+    #[test]
+    fn samples_every_frame() {
+        assert_eq!(
+            sampled_frames(SamplingPolicy::EveryFrame, 4, &[]),
+            [1, 2, 3, 4]
+        );
+    }
+
+    #[test]
+    fn samples_every_n_frames() {
+        assert_eq!(
+            sampled_frames(SamplingPolicy::EveryNFrames(3), 7, &[]),
+            [1, 4, 7]
+        );
+
+        // Zero is treated like one.
+        assert_eq!(
+            sampled_frames(SamplingPolicy::EveryNFrames(0), 3, &[]),
+            [1, 2, 3]
+        );
+    }
+
+    #[test]
+    fn samples_at_intervals() {
+        assert_eq!(
+            sampled_frames(SamplingPolicy::Interval(Duration::from_secs(3600)), 3, &[]),
+            [1]
+        );
+        assert_eq!(
+            sampled_frames(SamplingPolicy::Interval(Duration::ZERO), 3, &[]),
+            [1, 2, 3]
+        );
+    }
+
+    #[test]
+    fn samples_on_request() {
+        assert_eq!(sampled_frames(SamplingPolicy::OnDemand, 5, &[2, 4]), [2, 4]);
+
+        // A requested sample restarts the count until the next regular one.
+        assert_eq!(
+            sampled_frames(SamplingPolicy::EveryNFrames(3), 5, &[2]),
+            [1, 2, 5]
+        );
+    }
+
+    #[test]
+    fn records_history_only_for_sampled_frames() {
+        let mut world = World::new();
+
+        let mut memory_usage = MemoryUsage::default();
+        memory_usage.register_type_with_kind::<Buffer>(TypeKind::Resource);
+        world.insert_resource(memory_usage);
+        world.insert_resource(MemoryConfig {
+            sampling: SamplingPolicy::EveryNFrames(2),
+            history_length: 2,
+            ..Default::default()
+        });
+        world.insert_resource(Buffer { data: Vec::new() });
+
+        let mut first_stage = SystemStage::parallel();
+        first_stage.add_system(begin_frame);
+
+        let mut update_stage = SystemStage::parallel();
+        update_stage.add_system(update_stats_for_resource::<Buffer, ForwardingEstimator>);
+
+        for frame in 1..=5 {
+            world.get_resource_mut::<Buffer>().unwrap().data = vec![0; frame];
+
+            first_stage.run(&mut world);
+            update_stage.run(&mut world);
+        }
+
+        // Each sample is recorded once its frame is over.
+        first_stage.run(&mut world);
+
+        let memory_usage = world.get_resource::<MemoryUsage>().unwrap();
+        let samples: Vec<_> = memory_usage
+            .get_history::<Buffer>()
+            .unwrap()
+            .samples()
+            .iter()
+            .map(|sample| (sample.frame, sample.stats.total_heap_bytes))
+            .collect();
+
+        assert_eq!(samples, [(3, 3), (5, 5)]);
+    }
+}
@@ -0,0 +1,119 @@
+use std::any::{Any, TypeId};
+
+/// Describes a type that is registered for memory usage tracking.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct TypeInfo {
+    /// The [`TypeId`] of the type.
+    pub type_id: TypeId,
+
+    /// The full name of the type, as given by [`std::any::type_name`].
+    pub type_name: &'static str,
+
+    /// The name of the type without any module paths, suitable for display.
+    pub short_name: String,
+
+    /// What kind of data the type represents.
+    pub kind: TypeKind,
+}
+
+impl TypeInfo {
+    /// Returns the [`TypeInfo`] for the given type.
+    pub fn of<T>(kind: TypeKind) -> Self
+    where
+        T: Any,
+    {
+        let type_name = std::any::type_name::<T>();
+
+        Self {
+            type_id: TypeId::of::<T>(),
+            type_name,
+            short_name: short_type_name(type_name),
+            kind,
+        }
+    }
+}
+
+/// The kind of data that a registered type represents.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum TypeKind {
+    /// A [`Component`][bevy::ecs::component::Component].
+    Component,
+
+    /// A [`Resource`][bevy::ecs::system::Resource].
+    Resource,
+
+    /// An [`Asset`][bevy::asset::Asset].
+    Asset,
+
+    /// The extracted or prepared form of a render asset, which lives in the
+    /// render world.
+    RenderAsset,
+
+    /// A type whose stats are updated by a custom system.
+    Custom,
+}
+
+/// Strips the module paths from a type name as given by
+/// [`std::any::type_name`].
+///
+/// Generic arguments are shortened as well, so
+/// `bevy_asset::handle::Handle<bevy_render::texture::image::Image>` becomes
+/// `Handle<Image>`.
+pub(crate) fn short_type_name(full_name: &str) -> String {
+    let mut short_name = String::with_capacity(full_name.len());
+    let mut rest = full_name;
+
+    while let Some(index) = rest.find(|c: char| "<>,;()[]& ".contains(c)) {
+        let (segment, tail) = rest.split_at(index);
+        short_name.push_str(last_path_segment(segment));
+
+        let (delimiter, tail) = tail.split_at(1);
+        short_name.push_str(delimiter);
+
+        rest = tail;
+    }
+
+    short_name.push_str(last_path_segment(rest));
+
+    short_name
+}
+
+#[inline]
+fn last_path_segment(path: &str) -> &str {
+    path.rsplit("::").next().unwrap_or(path)
+}
+
+/***************************************************************************************************
+
+                             dMMMMMMP dMMMMMP .dMMMb dMMMMMMP .dMMMb
+                               dMP   dMP     dMP" VP   dMP   dMP" VP
+                              dMP   dMMMP    VMMMb    dMP    VMMMb
+                             dMP   dMP     dP .dMP   dMP   dP .dMP
+                            dMP   dMMMMMP  VMMMP"   dMP    VMMMP"
+
+***************************************************************************************************/
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn strips_module_paths() {
+        assert_eq!(short_type_name("bevy_render::mesh::mesh::Mesh"), "Mesh");
+        assert_eq!(short_type_name("usize"), "usize");
+    }
+
+    #[test]
+    fn strips_module_paths_of_generic_arguments() {
+        assert_eq!(
+            short_type_name("bevy_asset::handle::Handle<bevy_render::texture::image::Image>"),
+            "Handle<Image>"
+        );
+        assert_eq!(
+            short_type_name(
+                "std::collections::HashMap<alloc::string::String, (u8, [foo::Bar; 4])>"
+            ),
+            "HashMap<String, (u8, [Bar; 4])>"
+        );
+    }
+}